@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use tracing::info;
+use twilight_http::request::channel::reaction::RequestReactionType;
+use twilight_model::channel::message::{AllowedMentions, EmojiReactionType};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker};
+
+use crate::AppState;
+
+/// The file in the data directory each guild's announcement default is stored in.
+pub const ANNOUNCEMENTS_FILE: &str = "announcements.json";
+
+/// The emoji the bot reacts to a message with once a task has been created from it.
+pub const TRACKED_EMOJI: &str = "📌";
+
+/// Whether tasks created from messages are announced by default, keyed by guild ID.
+pub type AnnounceDefaults = HashMap<u64, bool>;
+
+/// Checks whether tasks created from messages in the guild are announced without asking.
+pub async fn is_default(state: &AppState, guild_id: Option<Id<GuildMarker>>) -> bool {
+    let Some(guild_id) = guild_id else {
+        return false;
+    };
+    state
+        .announcements
+        .read()
+        .await
+        .get(&guild_id.get())
+        .copied()
+        .unwrap_or(false)
+}
+
+/// Publicly marks the message as tracked in Todoist: reacts to it with 📌 and replies "📌 Tracked
+/// in Todoist by @user", without pinging the user.
+///
+/// Only who tracked the message is shown, the task itself stays in the ephemeral card. Returns
+/// `false` without doing anything if the message has already been announced.
+pub async fn announce(
+    state: &AppState,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    user_id: Id<UserMarker>,
+) -> Result<bool> {
+    let message = state
+        .client
+        .message(channel_id, message_id)
+        .await?
+        .model()
+        .await?;
+    let is_announced = message.reactions.iter().any(|reaction| {
+        let is_tracked_emoji =
+            matches!(&reaction.emoji, EmojiReactionType::Unicode { name } if name == TRACKED_EMOJI);
+        reaction.me && is_tracked_emoji
+    });
+    if is_announced {
+        return Ok(false);
+    }
+
+    state
+        .client
+        .create_reaction(
+            channel_id,
+            message_id,
+            &RequestReactionType::Unicode {
+                name: TRACKED_EMOJI,
+            },
+        )
+        .await?;
+    state
+        .client
+        .create_message(channel_id)
+        .reply(message_id)
+        .content(&format!(
+            "{} Tracked in Todoist by <@{}>",
+            TRACKED_EMOJI, user_id
+        ))
+        .allowed_mentions(Some(&AllowedMentions::default()))
+        .await?;
+    info!("Announced task created from message {}", message_id);
+    Ok(true)
+}
@@ -9,7 +9,10 @@ use thiserror::Error;
 use todoist_bot::llm::Provider;
 use todoist_bot::llm::claude::ClaudeHttpClient;
 use todoist_bot::llm::openai::OpenAIProvider;
-use todoist_bot::{AppState, interactions, retrieve_current_user, routes};
+use todoist_bot::{
+    AppState, announce, board, card_refs, digest, gateway, interactions, reminders,
+    retrieve_current_user, review, routes,
+};
 use tokio::net::TcpListener;
 use tracing::info;
 use twilight_commands::executor::{ContextCommands, SlashCommands};
@@ -18,8 +21,16 @@ use twilight_model::application::command::Command;
 use twilight_model::id::Id;
 
 use todoist_bot::emoji::Emojis;
+use todoist_bot::interactions::autocomplete::{self, AutocompleteHandlers};
+use todoist_bot::interactions::components::ComponentHandlers;
+use todoist_bot::interactions::dispatch::Dispatcher;
+use todoist_bot::interactions::picker::{PendingComments, RecentProjects};
 use todoist_bot::interactions::verifier::Verifier;
+use todoist_bot::scheduler::Scheduler;
+use todoist_bot::storage::JsonStore;
+use todoist_bot::todoist::cache::TodoistCache;
 use todoist_bot::todoist::http::TodoistHttpClient;
+use todoist_bot::todoist::webhook::WebhookVerifier;
 
 #[derive(Debug, Error)]
 enum MissingEnvironemntVariable {
@@ -43,19 +54,37 @@ async fn main() -> Result<()> {
     let todoist_token =
         env::var("TODOIST_API_TOKEN").map_err(|_| MissingEnvironemntVariable::TodoistApiToken)?;
     let todoist_client = Arc::new(TodoistHttpClient::new(&todoist_token));
+    let todoist_cache = Arc::new(TodoistCache::new(Arc::clone(&todoist_client)));
 
     let llm_provider = initialize_llm_provider()?;
 
-    let interaction_key =
-        env::var("INTERACTION_KEY").map_err(|_| MissingEnvironemntVariable::InteractionKey)?;
-    let verifier = Arc::new(Verifier::try_new(&interaction_key)?);
+    // Interactions received over the gateway come from Discord directly, so are not signed
+    let verifier = if gateway::interactions_over_gateway() {
+        None
+    } else {
+        let interaction_key =
+            env::var("INTERACTION_KEY").map_err(|_| MissingEnvironemntVariable::InteractionKey)?;
+        Some(Arc::new(Verifier::try_new(&interaction_key)?))
+    };
 
     let bot_token = env::var("BOT_TOKEN").map_err(|_| MissingEnvironemntVariable::BotToken)?;
-    let client = Arc::new(Client::new(bot_token));
+    let client = Arc::new(Client::new(bot_token.clone()));
 
     let (context_commands, slash_commands) = register_commands();
     let context_commands = Arc::new(context_commands);
     let slash_commands = Arc::new(slash_commands);
+    let autocomplete = Arc::new(register_autocomplete());
+    let component_handlers = Arc::new(register_components());
+
+    let digests = Arc::new(JsonStore::open(digest::DIGESTS_FILE).await?);
+    let reminders = Arc::new(JsonStore::open(reminders::REMINDERS_FILE).await?);
+    let review = Arc::new(JsonStore::open(review::REVIEW_FILE).await?);
+    let cards = Arc::new(JsonStore::open(card_refs::CARDS_FILE).await?);
+    let boards = Arc::new(JsonStore::open(board::BOARDS_FILE).await?);
+    let announcements = Arc::new(JsonStore::open(announce::ANNOUNCEMENTS_FILE).await?);
+    let todoist_webhook = env::var("TODOIST_CLIENT_SECRET")
+        .ok()
+        .map(|secret| Arc::new(WebhookVerifier::new(&secret)));
 
     let app_id = {
         let response = client.current_user_application().await?;
@@ -68,7 +97,19 @@ async fn main() -> Result<()> {
         client,
         context_commands,
         slash_commands,
+        autocomplete,
+        component_handlers,
         todoist_client,
+        todoist_cache,
+        recent_projects: Arc::new(RecentProjects::default()),
+        pending_comments: Arc::new(PendingComments::default()),
+        digests,
+        reminders,
+        review,
+        cards,
+        boards,
+        announcements,
+        todoist_webhook,
         llm_provider,
     };
 
@@ -82,16 +123,39 @@ async fn main() -> Result<()> {
         &state.client,
         &state.context_commands,
         &state.slash_commands,
+        &state.autocomplete,
         guild_id,
     )
     .await?;
 
-    let app = Router::new()
+    Arc::clone(&state.todoist_cache).spawn_refresh();
+    register_jobs().spawn(Arc::new(state.clone()));
+    let dispatcher = Dispatcher::new(Arc::new(state.clone()));
+    if gateway::is_enabled() {
+        gateway::spawn(Arc::new(state.clone()), dispatcher.clone(), bot_token);
+    }
+
+    let mut app = Router::new()
         .route("/_health", get(routes::health))
-        .route("/interactions", post(routes::interaction_callback))
-        .with_state(state);
+        .route("/todoist/webhook", post(routes::todoist_webhook));
+    if !gateway::interactions_over_gateway() {
+        app = app.route("/interactions", post(routes::interaction_callback));
+    }
+    let app = app.with_state(routes::RouterState {
+        app: state,
+        dispatcher,
+    });
 
-    let listener = TcpListener::bind("0.0.0.0:3000").await?;
+    // In gateway mode nothing needs to reach the bot, so only listen locally unless configured
+    let bind_address = env::var("BIND_ADDRESS").unwrap_or_else(|_| {
+        if gateway::interactions_over_gateway() {
+            "127.0.0.1:3000".to_string()
+        } else {
+            "0.0.0.0:3000".to_string()
+        }
+    });
+    info!("Listening on {}", bind_address);
+    let listener = TcpListener::bind(&bind_address).await?;
     axum::serve(listener, app).await?;
 
     Ok(())
@@ -101,6 +165,7 @@ async fn update_commands(
     client: &Client,
     context_commands: &ContextCommands<AppState>,
     slash_commands: &SlashCommands<AppState>,
+    autocomplete: &AutocompleteHandlers<AppState>,
     guild_id: Option<String>,
 ) -> Result<()> {
     let application_id = {
@@ -111,6 +176,7 @@ async fn update_commands(
     let client = client.interaction(application_id);
     let mut commands: Vec<Command> = context_commands.into();
     commands.append(&mut slash_commands.build_commands());
+    autocomplete.enable_autocomplete(&mut commands);
 
     match guild_id {
         Some(guild_id) => {
@@ -131,13 +197,96 @@ fn register_commands() -> (ContextCommands<AppState>, SlashCommands<AppState>) {
     let mut context_commands = ContextCommands::default();
 
     context_commands.register("Add To-Do", interactions::command_handlers::add_reminder);
+    context_commands.register(
+        "Add Multiple To-Dos",
+        interactions::command_handlers::add_multiple_reminders,
+    );
+    context_commands.register(
+        "Add as comment to task…",
+        interactions::command_handlers::add_comment,
+    );
 
     let mut command_executor = SlashCommands::default();
     command_executor.register(interactions::command_handlers::handle_today);
+    command_executor.register(interactions::command_handlers::handle_add);
+    command_executor.register(interactions::command_handlers::handle_complete);
+    command_executor.register(interactions::command_handlers::handle_projects);
+    command_executor.register(interactions::command_handlers::handle_project_view);
+    command_executor.register(interactions::command_handlers::handle_label_add);
+    command_executor.register(interactions::command_handlers::handle_label_remove);
+    command_executor.register(interactions::command_handlers::handle_comment);
+    command_executor.register(interactions::command_handlers::handle_comments);
+    command_executor.register(interactions::command_handlers::handle_digest_schedule);
+    command_executor.register(interactions::command_handlers::handle_digest_disable);
+    command_executor.register(interactions::command_handlers::handle_digest_preview);
+    command_executor.register(interactions::command_handlers::handle_review_week);
+    command_executor.register(interactions::command_handlers::handle_settings_announce);
+    command_executor.register(interactions::command_handlers::handle_board_create);
+    command_executor.register(interactions::command_handlers::handle_board_delete);
 
     (context_commands, command_executor)
 }
 
+fn register_autocomplete() -> AutocompleteHandlers<AppState> {
+    let mut autocomplete = AutocompleteHandlers::default();
+
+    autocomplete.register("add", "project", autocomplete::projects);
+    autocomplete.register("add", "section", autocomplete::sections);
+    autocomplete.register("complete", "task", autocomplete::open_tasks);
+    autocomplete.register("project view", "project", autocomplete::projects);
+    autocomplete.register("label add", "task", autocomplete::open_tasks);
+    autocomplete.register("label add", "label", autocomplete::labels);
+    autocomplete.register("label remove", "task", autocomplete::open_tasks);
+    autocomplete.register("label remove", "label", autocomplete::labels);
+    autocomplete.register("comment", "task", autocomplete::open_tasks);
+    autocomplete.register("comments", "task", autocomplete::open_tasks);
+    autocomplete.register("digest schedule", "timezone", autocomplete::timezones);
+    autocomplete.register("board create", "project", autocomplete::projects);
+    autocomplete.register("board delete", "project", autocomplete::projects);
+
+    autocomplete
+}
+
+fn register_jobs() -> Scheduler<AppState> {
+    let mut scheduler = Scheduler::default();
+
+    scheduler.every("digests", digest::CHECK_INTERVAL, digest::run_due_digests);
+    scheduler.every(
+        "reminders",
+        reminders::CHECK_INTERVAL,
+        reminders::run_due_reminders,
+    );
+    scheduler.every(
+        "review",
+        review::CHECK_INTERVAL,
+        review::run_scheduled_review,
+    );
+    scheduler.every("boards", board::REFRESH_INTERVAL, board::run_board_refresh);
+
+    scheduler
+}
+
+fn register_components() -> ComponentHandlers<AppState> {
+    let mut components = ComponentHandlers::default();
+
+    components.register(interactions::component_handlers::handle_project_select);
+    components.register(interactions::component_handlers::handle_project_page);
+    components.register(interactions::component_handlers::handle_section_select);
+    components.register(interactions::component_handlers::handle_section_page);
+    components.register(interactions::component_handlers::handle_task_project_select);
+    components.register(interactions::component_handlers::handle_label_select);
+    components.register(interactions::component_handlers::handle_comment_task_select);
+    components.register(interactions::component_handlers::handle_comment_task_page);
+    components.register(interactions::component_handlers::handle_project_view_page);
+    components.register(interactions::component_handlers::handle_section_tasks);
+    components.register(interactions::component_handlers::handle_announce);
+    components.register(interactions::component_handlers::handle_complete_task);
+    components.register(interactions::component_handlers::handle_reopen_task);
+    components.register(interactions::component_handlers::handle_snooze_reminder);
+
+    components
+}
+
 fn initialize_llm_provider() -> Result<Arc<Provider>> {
     let llm_provider = env::var("LLM_PROVIDER")
         .ok()
@@ -163,7 +312,23 @@ fn initialize_claude() -> Result<Arc<Provider>> {
             .expect("Failed to read Claude system prompt from specified path")
     });
 
-    let claude_client = ClaudeHttpClient::new(&claude_token, &claude_model, system_prompt);
+    let multiple_system_prompt = env::var("CLAUDE_MULTIPLE_SYSTEM_PROMPT_PATH")
+        .ok()
+        .map(|path| {
+            info!(
+                "Using Claude multiple reminder system prompt from path: {}",
+                path
+            );
+            std::fs::read_to_string(path)
+                .expect("Failed to read Claude multiple reminder system prompt from specified path")
+        });
+
+    let claude_client = ClaudeHttpClient::new(
+        &claude_token,
+        &claude_model,
+        system_prompt,
+        multiple_system_prompt,
+    );
     Ok(Arc::new(claude_client))
 }
 
@@ -0,0 +1,296 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use twilight_http::api_error::{ApiError, GeneralApiError};
+use twilight_http::error::ErrorType;
+use twilight_model::channel::message::component::{Container, SeparatorSpacingSize};
+use twilight_model::channel::message::{Component, MessageFlags};
+use twilight_model::id::Id;
+use twilight_util::builder::message::{ContainerBuilder, SeparatorBuilder, TextDisplayBuilder};
+
+use crate::AppState;
+use crate::default_timezone;
+use crate::interactions::MAX_TEXT_DISPLAY_LENGTH;
+use crate::interactions::browse::section_tasks;
+use crate::interactions::picker::NO_SECTION;
+use crate::todoist::http::models::Task;
+use crate::todoist::tree::ProjectTree;
+use crate::todoist::webhook::WebhookEvent;
+
+/// The file in the data directory boards are stored in.
+pub const BOARDS_FILE: &str = "boards.json";
+
+/// How often every board is refreshed, in case a change was missed by the webhook.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The maximum number of sections shown on a board.
+const MAX_SECTIONS: usize = 10;
+
+/// The maximum number of tasks listed for each section of a board.
+const MAX_TASKS_PER_SECTION: usize = 10;
+
+/// The length kept free for the footer of a board, and for the "…and more" line of each section.
+const RESERVED_LENGTH: usize = 100;
+
+/// The error code Discord responds with when the message does not exist.
+const UNKNOWN_MESSAGE: u64 = 10008;
+
+/// The error code Discord responds with when the channel does not exist.
+const UNKNOWN_CHANNEL: u64 = 10003;
+
+/// Boards keyed by the ID of the message they are posted as.
+pub type Boards = HashMap<u64, Board>;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Board {
+    pub project_id: String,
+    pub channel_id: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Builds the board of a project's open tasks, grouped by section with a marker showing whether
+/// each task is overdue or due today.
+pub async fn build_board(state: &AppState, project_id: &str, timezone: Tz) -> Result<Container> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    let path = tree
+        .path(project_id)
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+    let sections = state.todoist_cache.sections().await?;
+    let tasks = state.todoist_cache.open_tasks().await?;
+
+    let mut sections = sections
+        .iter()
+        .filter(|section| {
+            section.project_id == project_id && !section.is_archived && !section.is_deleted
+        })
+        .collect::<Vec<_>>();
+    sections.sort_by_key(|section| section.section_order);
+
+    // Tasks without a section are listed first, as they are in Todoist
+    let groups = std::iter::once((NO_SECTION, "No section"))
+        .chain(
+            sections
+                .iter()
+                .map(|section| (section.id.as_str(), section.name.as_str())),
+        )
+        .map(|(section_id, name)| (name, section_tasks(&tasks, project_id, section_id)))
+        .filter(|(_, tasks)| !tasks.is_empty())
+        .collect::<Vec<_>>();
+    let total = groups.iter().map(|(_, tasks)| tasks.len()).sum::<usize>();
+
+    let header = format!(
+        "## 📋 {}\n-# {} open tasks · Updated <t:{}:R>",
+        path,
+        total,
+        Utc::now().timestamp()
+    );
+    // Discord limits the length of the text in the whole message, so sections stop being added
+    // once the next one does not fit
+    let mut remaining =
+        MAX_TEXT_DISPLAY_LENGTH.saturating_sub(header.chars().count() + RESERVED_LENGTH);
+    let mut container = ContainerBuilder::new()
+        .component(TextDisplayBuilder::new(header).build())
+        .component(
+            SeparatorBuilder::new()
+                .divider(true)
+                .spacing(SeparatorSpacingSize::Small)
+                .build(),
+        );
+
+    let today = Utc::now().with_timezone(&timezone).date_naive();
+    let mut shown = 0;
+    for (name, tasks) in groups.iter().take(MAX_SECTIONS) {
+        let mut content = format!("### {} ({})\n", name, tasks.len());
+        let mut length = content.chars().count() + RESERVED_LENGTH;
+        let mut listed = 0;
+        for task in tasks.iter().take(MAX_TASKS_PER_SECTION) {
+            let line = format!("- {}\n", board_line(task, today, timezone));
+            length += line.chars().count();
+            if length > remaining {
+                break;
+            }
+            content.push_str(&line);
+            listed += 1;
+        }
+        if listed == 0 {
+            break;
+        }
+        if tasks.len() > listed {
+            content.push_str(&format!("-# …and {} more\n", tasks.len() - listed));
+        }
+        remaining = remaining.saturating_sub(content.chars().count());
+        container = container.component(TextDisplayBuilder::new(content).build());
+        shown += 1;
+    }
+    if groups.len() > shown {
+        container = container.component(
+            TextDisplayBuilder::new(format!(
+                "-# {} more sections are not shown",
+                groups.len() - shown
+            ))
+            .build(),
+        );
+    }
+    if groups.is_empty() {
+        container = container
+            .component(TextDisplayBuilder::new("There are no open tasks.".to_string()).build());
+    }
+
+    Ok(container.build())
+}
+
+/// Formats the task as a link, prefixed with 🔴 if it is overdue or 🟡 if it is due today and
+/// followed by its due date.
+fn board_line(task: &Task, today: NaiveDate, timezone: Tz) -> String {
+    let Some((due, due_date)) = task.due.as_ref().and_then(|due| {
+        DateTime::<FixedOffset>::try_from(due.clone())
+            .ok()
+            .map(|due_date| (due, due_date))
+    }) else {
+        return format!("[{}]({})", task.content, task.get_url());
+    };
+
+    let day = if due.is_date_only() {
+        due_date.date_naive()
+    } else {
+        due_date.with_timezone(&timezone).date_naive()
+    };
+    let marker = if day < today {
+        "🔴 "
+    } else if day == today {
+        "🟡 "
+    } else {
+        ""
+    };
+    let style = if due.is_date_only() { "d" } else { "f" };
+    format!(
+        "{}[{}]({}) <t:{}:{}>",
+        marker,
+        task.content,
+        task.get_url(),
+        due_date.timestamp(),
+        style
+    )
+}
+
+/// Posts a board for the project in the channel, returning the ID of its message.
+pub async fn post_board(state: &AppState, channel_id: u64, project_id: &str) -> Result<u64> {
+    let container = build_board(state, project_id, default_timezone()).await?;
+    let message = state
+        .client
+        .create_message(Id::new(channel_id))
+        .components(&[container.into()])
+        .flags(MessageFlags::IS_COMPONENTS_V2)
+        .await?
+        .model()
+        .await?;
+    if let Err(e) = state
+        .client
+        .create_pin(message.channel_id, message.id)
+        .await
+    {
+        warn!("Failed to pin board {}: {}", message.id, e);
+    }
+
+    state
+        .boards
+        .update(|boards| {
+            boards.insert(
+                message.id.get(),
+                Board {
+                    project_id: project_id.to_string(),
+                    channel_id,
+                    created_at: Utc::now(),
+                },
+            )
+        })
+        .await?;
+    info!(
+        "Posted board {} for project {} in channel {}",
+        message.id, project_id, channel_id
+    );
+    Ok(message.id.get())
+}
+
+/// Rebuilds the board and edits its message.
+async fn refresh_board(state: &AppState, message_id: u64, board: &Board) -> Result<()> {
+    let container = build_board(state, &board.project_id, default_timezone()).await?;
+    let components = [Component::from(container)];
+    state
+        .client
+        .update_message(Id::new(board.channel_id), Id::new(message_id))
+        .components(Some(components.as_slice()))
+        .await?;
+    debug!("Refreshed board {}", message_id);
+    Ok(())
+}
+
+/// Refreshes every board, or only the boards of the project if one is given.
+pub async fn refresh_boards(state: &AppState, project_id: Option<&str>) -> Result<()> {
+    let boards = state
+        .boards
+        .read()
+        .await
+        .iter()
+        .filter(|(_, board)| project_id.is_none_or(|project_id| board.project_id == project_id))
+        .map(|(message_id, board)| (*message_id, board.clone()))
+        .collect::<Vec<_>>();
+    for (message_id, board) in boards {
+        match refresh_board(state, message_id, &board).await {
+            Ok(()) => {}
+            // The board was deleted by hand, so it will never refresh again
+            Err(e) if is_unknown_message(&e) => {
+                info!(
+                    "Board {} no longer exists, no longer refreshing it",
+                    message_id
+                );
+                state
+                    .boards
+                    .update(|boards| boards.remove(&message_id))
+                    .await?;
+            }
+            Err(e) => warn!("Failed to refresh board {}: {}", message_id, e),
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether the request failed because the message or its channel no longer exists.
+fn is_unknown_message(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<twilight_http::Error>()
+        .is_some_and(|error| {
+            matches!(
+                error.kind(),
+                ErrorType::Response {
+                    error: ApiError::General(GeneralApiError {
+                        code: UNKNOWN_MESSAGE | UNKNOWN_CHANNEL,
+                        ..
+                    }),
+                    ..
+                }
+            )
+        })
+}
+
+/// Refreshes every board on a schedule.
+pub async fn run_board_refresh(state: Arc<AppState>) -> Result<()> {
+    refresh_boards(&state, None).await
+}
+
+/// Refreshes the boards affected by an event from the Todoist webhook: the boards of the task's
+/// project for task events, and every board when projects or sections change.
+pub async fn handle_webhook_event(state: &AppState, event: &WebhookEvent) -> Result<()> {
+    if let Some(item) = event.item() {
+        refresh_boards(state, Some(&item.project_id)).await
+    } else if event.event_name.starts_with("project:") || event.event_name.starts_with("section:") {
+        refresh_boards(state, None).await
+    } else {
+        Ok(())
+    }
+}
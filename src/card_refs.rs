@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use twilight_model::application::interaction::Interaction;
+use twilight_model::channel::Message;
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::Container;
+use twilight_model::id::Id;
+
+use crate::AppState;
+use crate::interactions::cards::closed_card;
+use crate::interactions::component_handlers;
+use crate::reminders::reminder_card;
+use crate::todoist;
+use crate::todoist::http::models::Task;
+use crate::todoist::webhook::WebhookEvent;
+
+/// The file in the data directory card references are stored in.
+pub const CARDS_FILE: &str = "cards.json";
+
+/// How long Discord allows an interaction response to be edited with the interaction token.
+const INTERACTION_TOKEN_LIFETIME: TimeDelta = TimeDelta::minutes(15);
+
+/// The Discord messages showing each task, keyed by task ID. A task's cards are forgotten once it
+/// is completed or deleted and its cards have been closed.
+pub type CardRefs = HashMap<String, Vec<CardRef>>;
+
+/// What a card shows, which decides how it is rendered when the task changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CardKind {
+    /// The task's card, with its pickers.
+    #[default]
+    Task,
+    /// A reminder that the task is due, with buttons to complete or snooze it.
+    Reminder,
+}
+
+/// A Discord message showing a task, which is edited when the task changes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CardRef {
+    /// An interaction response, such as an ephemeral "Created task" card, which can only be edited
+    /// with the interaction token until it expires.
+    Interaction {
+        /// The ID of the interaction, used to recognise the response when its components are used.
+        #[serde(default)]
+        interaction_id: u64,
+        token: String,
+        expires_at: DateTime<Utc>,
+    },
+    /// A message sent by the bot.
+    Message {
+        channel_id: u64,
+        message_id: u64,
+        #[serde(default)]
+        kind: CardKind,
+    },
+}
+
+impl CardRef {
+    /// References the response to the interaction.
+    pub fn interaction(interaction: &Interaction) -> Self {
+        Self::Interaction {
+            interaction_id: interaction.id.get(),
+            token: interaction.token.clone(),
+            expires_at: Utc::now() + INTERACTION_TOKEN_LIFETIME,
+        }
+    }
+
+    /// Checks whether this card is the message.
+    fn is_message(&self, message: &Message) -> bool {
+        match self {
+            Self::Interaction { interaction_id, .. } => message
+                .interaction_metadata
+                .as_ref()
+                .is_some_and(|metadata| metadata.id.get() == *interaction_id),
+            Self::Message { message_id, .. } => message.id.get() == *message_id,
+        }
+    }
+
+    fn kind(&self) -> CardKind {
+        match self {
+            Self::Interaction { .. } => CardKind::Task,
+            Self::Message { kind, .. } => *kind,
+        }
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        match self {
+            Self::Interaction { expires_at, .. } => *expires_at <= now,
+            Self::Message { .. } => false,
+        }
+    }
+}
+
+/// Remembers that the card shows the task, forgetting any expired cards.
+pub async fn record(state: &AppState, task_id: &str, card: CardRef) -> Result<()> {
+    let now = Utc::now();
+    state
+        .cards
+        .update(|cards| {
+            let refs = cards.entry(task_id.to_string()).or_default();
+            if !refs.contains(&card) {
+                refs.push(card);
+            }
+            for refs in cards.values_mut() {
+                refs.retain(|card| !card.is_expired(now));
+            }
+            cards.retain(|_, refs| !refs.is_empty());
+        })
+        .await
+}
+
+/// Forgets every card showing the task.
+pub async fn forget(state: &AppState, task_id: &str) -> Result<()> {
+    state
+        .cards
+        .update(|cards| {
+            cards.remove(task_id);
+        })
+        .await
+}
+
+/// Replaces every card showing the task with the container `render` returns for its kind, except
+/// the card that is `skip`.
+pub async fn update_cards(
+    state: &AppState,
+    task_id: &str,
+    render: impl Fn(CardKind) -> Container,
+    skip: Option<&Message>,
+) -> Result<()> {
+    let now = Utc::now();
+    let refs = state
+        .cards
+        .read()
+        .await
+        .get(task_id)
+        .cloned()
+        .unwrap_or_default();
+
+    for card in refs
+        .iter()
+        .filter(|card| !card.is_expired(now))
+        .filter(|card| !skip.is_some_and(|message| card.is_message(message)))
+    {
+        let components = [Component::from(render(card.kind()))];
+        let result = match card {
+            CardRef::Interaction { token, .. } => state
+                .client
+                .interaction(state.app_id)
+                .update_response(token)
+                .components(Some(components.as_slice()))
+                .await
+                .map(|_| ()),
+            CardRef::Message {
+                channel_id,
+                message_id,
+                ..
+            } => state
+                .client
+                .update_message(Id::new(*channel_id), Id::new(*message_id))
+                .components(Some(components.as_slice()))
+                .await
+                .map(|_| ()),
+        };
+        match result {
+            Ok(()) => debug!("Updated card {:?} for task {}", card, task_id),
+            Err(e) => warn!("Failed to update card for task {}: {}", task_id, e),
+        }
+    }
+    Ok(())
+}
+
+/// Re-renders every card showing the task from its current state in Todoist, along with the cards
+/// of its parent task, whose list of subtasks may have changed. The cards of a task that has been
+/// completed or deleted are closed and then forgotten.
+///
+/// `skip` is the message an interaction is already updating in its response, so the card a
+/// component was used on keeps the picker the handler chose for it.
+pub async fn refresh_cards(state: &AppState, task_id: &str, skip: Option<&Message>) -> Result<()> {
+    let task = todoist::get_task(&state.todoist_client, task_id).await?;
+    if task.is_deleted || task.checked {
+        let status = if task.is_deleted {
+            "Deleted"
+        } else {
+            "Completed"
+        };
+        close_cards(state, &task.id, &task.content, status, skip).await?;
+    } else {
+        update_live_cards(state, &task, skip).await?;
+    }
+
+    if let Some(parent_id) = &task.parent_id
+        && state.cards.read().await.contains_key(parent_id)
+    {
+        let parent = todoist::get_task(&state.todoist_client, parent_id).await?;
+        if !parent.checked && !parent.is_deleted {
+            update_live_cards(state, &parent, skip).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-renders the cards of an open task, keeping reminders as reminders.
+async fn update_live_cards(state: &AppState, task: &Task, skip: Option<&Message>) -> Result<()> {
+    let live = component_handlers::live_card(state, task).await?;
+    let reminder = reminder_card(task)?;
+    update_cards(
+        state,
+        &task.id,
+        |kind| match kind {
+            CardKind::Task => live.clone(),
+            CardKind::Reminder => reminder.clone(),
+        },
+        skip,
+    )
+    .await
+}
+
+/// Replaces every card of the task with its closed card and forgets them, as a closed task's
+/// cards no longer change.
+async fn close_cards(
+    state: &AppState,
+    task_id: &str,
+    content: &str,
+    status: &str,
+    skip: Option<&Message>,
+) -> Result<()> {
+    update_cards(state, task_id, |_| closed_card(content, status), skip).await?;
+    forget(state, task_id).await
+}
+
+/// Refreshes the task's cards in the background after the bot changed it.
+pub fn spawn_refresh(state: Arc<AppState>, task_id: String, skip: Option<Message>) {
+    tokio::spawn(async move {
+        if let Err(e) = refresh_cards(&state, &task_id, skip.as_ref()).await {
+            warn!("Failed to refresh cards for task {}: {}", task_id, e);
+        }
+    });
+}
+
+/// Updates the cache and the cards of the task an event from the Todoist webhook is about.
+pub async fn handle_webhook_event(state: &AppState, event: &WebhookEvent) -> Result<()> {
+    debug!("Handling Todoist webhook event {}", event.event_name);
+    if event.event_name.starts_with("item:") || event.event_name.starts_with("note:") {
+        state.todoist_cache.invalidate_tasks().await;
+    } else {
+        state.todoist_cache.invalidate_structure().await;
+    }
+
+    let Some(item) = event.item() else {
+        return Ok(());
+    };
+    match event.event_name.as_str() {
+        "item:completed" => {
+            info!("Task {} was completed in Todoist", item.id);
+            close_cards(state, &item.id, &item.content, "Completed", None).await?;
+        }
+        "item:deleted" => {
+            info!("Task {} was deleted in Todoist", item.id);
+            close_cards(state, &item.id, &item.content, "Deleted", None).await?;
+        }
+        "item:updated" | "item:uncompleted" => {
+            debug!("Task {} was changed in Todoist", item.id);
+            refresh_cards(state, &item.id, None).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_refs_default_to_task_cards() {
+        let card: CardRef =
+            serde_json::from_str(r#"{"type":"message","channel_id":1,"message_id":2}"#).unwrap();
+        assert_eq!(card.kind(), CardKind::Task);
+
+        let card = CardRef::Message {
+            channel_id: 1,
+            message_id: 2,
+            kind: CardKind::Reminder,
+        };
+        let json = serde_json::to_string(&card).unwrap();
+        assert_eq!(serde_json::from_str::<CardRef>(&json).unwrap(), card);
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::channel::message::component::Container;
+use twilight_model::id::Id;
+
+use crate::AppState;
+use crate::interactions::cards::today_card;
+use crate::todoist;
+
+/// The file in the data directory digest schedules are stored in.
+pub const DIGESTS_FILE: &str = "digests.json";
+
+/// How often the schedules are checked for digests that are due.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long to wait before retrying a digest that failed to send. The delay doubles after each
+/// failed attempt.
+const RETRY_DELAY: TimeDelta = TimeDelta::minutes(1);
+
+/// How many times a digest is attempted before it is skipped until its next scheduled time.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Digest schedules keyed by the ID of the Discord user who created them.
+pub type Digests = HashMap<u64, DigestSchedule>;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DigestSchedule {
+    /// The cron expression the digest is sent on, e.g. "0 8 * * *".
+    pub cron: String,
+    /// The timezone the cron expression and due dates are evaluated in.
+    pub timezone: String,
+    pub target: DigestTarget,
+    pub created_at: DateTime<Utc>,
+    pub last_sent: Option<DateTime<Utc>>,
+    /// How many times sending the digest that is due has failed.
+    #[serde(default)]
+    pub failed_attempts: u32,
+    /// When the digest that is due is next attempted after it failed to send.
+    #[serde(default)]
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+/// Where a digest is sent.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DigestTarget {
+    DirectMessage,
+    Channel { channel_id: u64 },
+}
+
+impl DigestSchedule {
+    pub fn timezone(&self) -> Result<Tz> {
+        self.timezone
+            .parse::<Tz>()
+            .map_err(|e| anyhow!("Invalid timezone {}: {}", self.timezone, e))
+    }
+
+    /// Gets the next time the digest should be sent, which may be in the past if it is due.
+    pub fn next_run(&self) -> Result<Option<DateTime<Utc>>> {
+        let schedule = parse_schedule(&self.cron)?;
+        let timezone = self.timezone()?;
+        let since = self.last_sent.unwrap_or(self.created_at);
+        Ok(schedule
+            .after(&since.with_timezone(&timezone))
+            .next()
+            .map(|next| next.with_timezone(&Utc)))
+    }
+}
+
+/// Parses a cron expression, accepting the standard five field form ("minute hour day month
+/// weekday") as well as the six and seven field forms with seconds and years.
+pub fn parse_schedule(expression: &str) -> Result<Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    Schedule::from_str(&expression).map_err(|e| anyhow!(e))
+}
+
+/// Builds the digest: the tasks due today followed by any overdue tasks.
+pub async fn build_digest(state: &AppState, timezone: Tz) -> Result<Container> {
+    let tasks = todoist::get_tasks_due_today(&state.todoist_client, Some(timezone)).await?;
+    let overdue = todoist::get_overdue_tasks(&state.todoist_client, Some(timezone)).await?;
+    Ok(today_card(&tasks, &overdue))
+}
+
+/// Builds and sends the user's digest to its target.
+pub async fn send_digest(state: &AppState, user_id: u64, schedule: &DigestSchedule) -> Result<()> {
+    let container = build_digest(state, schedule.timezone()?).await?;
+    let channel_id = match schedule.target {
+        DigestTarget::DirectMessage => {
+            state
+                .client
+                .create_private_channel(Id::new(user_id))
+                .await?
+                .model()
+                .await?
+                .id
+        }
+        DigestTarget::Channel { channel_id } => Id::new(channel_id),
+    };
+
+    state
+        .client
+        .create_message(channel_id)
+        .components(&[container.into()])
+        .flags(MessageFlags::IS_COMPONENTS_V2)
+        .await?;
+    Ok(())
+}
+
+/// Sends every digest whose next scheduled time has passed.
+///
+/// A digest that fails to send is retried with an increasing delay, and skipped until its next
+/// scheduled time after [`MAX_ATTEMPTS`] failures. Digests missed while the bot was offline are
+/// sent once when it starts.
+pub async fn run_due_digests(state: Arc<AppState>) -> Result<()> {
+    let now = Utc::now();
+    let due = state
+        .digests
+        .read()
+        .await
+        .iter()
+        .filter(|(_, schedule)| schedule.retry_at.is_none_or(|retry_at| retry_at <= now))
+        .filter(|(user_id, schedule)| match schedule.next_run() {
+            Ok(next_run) => next_run.is_some_and(|next_run| next_run <= now),
+            Err(e) => {
+                warn!("Invalid digest schedule for user {}: {}", user_id, e);
+                false
+            }
+        })
+        .map(|(user_id, schedule)| (*user_id, schedule.clone()))
+        .collect::<Vec<_>>();
+
+    for (user_id, schedule) in due {
+        debug!("Sending digest to user {}", user_id);
+        let result = send_digest(&state, user_id, &schedule).await;
+        match &result {
+            Ok(()) => info!("Sent digest to user {}", user_id),
+            Err(e) => warn!(
+                "Failed to send digest to user {} (attempt {} of {}): {}",
+                user_id,
+                schedule.failed_attempts + 1,
+                MAX_ATTEMPTS,
+                e
+            ),
+        }
+        state
+            .digests
+            .update(|digests| {
+                if let Some(schedule) = digests.get_mut(&user_id) {
+                    record_attempt(schedule, result.is_ok(), now);
+                }
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Records the result of sending the digest. A digest that failed is retried after a delay, until
+/// it has failed [`MAX_ATTEMPTS`] times.
+fn record_attempt(schedule: &mut DigestSchedule, sent: bool, now: DateTime<Utc>) {
+    if !sent {
+        schedule.failed_attempts += 1;
+    }
+    if sent || schedule.failed_attempts >= MAX_ATTEMPTS {
+        schedule.last_sent = Some(now);
+        schedule.failed_attempts = 0;
+        schedule.retry_at = None;
+        return;
+    }
+    schedule.retry_at = Some(now + RETRY_DELAY * 2i32.pow(schedule.failed_attempts - 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_five_field_expressions() {
+        let schedule = parse_schedule("30 8 * * *").unwrap();
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 9, 0, 0).unwrap();
+        assert_eq!(
+            schedule.after(&after).next(),
+            Some(Utc.with_ymd_and_hms(2025, 1, 2, 8, 30, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parses_six_and_seven_field_expressions() {
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let schedule = parse_schedule("15 0 12 * * *").unwrap();
+        assert_eq!(
+            schedule.after(&after).next(),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 15).unwrap())
+        );
+        assert!(parse_schedule(" 0 0 12 * * * 2030 ").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(parse_schedule("").is_err());
+        assert!(parse_schedule("every day").is_err());
+        assert!(parse_schedule("61 8 * * *").is_err());
+    }
+
+    fn schedule() -> DigestSchedule {
+        DigestSchedule {
+            cron: "0 8 * * *".to_string(),
+            timezone: "UTC".to_string(),
+            target: DigestTarget::DirectMessage,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            last_sent: None,
+            failed_attempts: 0,
+            retry_at: None,
+        }
+    }
+
+    #[test]
+    fn failed_digest_is_retried_with_backoff() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap();
+        let mut schedule = schedule();
+        record_attempt(&mut schedule, false, now);
+        assert_eq!(schedule.last_sent, None);
+        assert_eq!(schedule.retry_at, Some(now + RETRY_DELAY));
+        record_attempt(&mut schedule, false, now);
+        assert_eq!(schedule.retry_at, Some(now + RETRY_DELAY * 2));
+        // The digest is still due until it is sent
+        assert_eq!(schedule.next_run().unwrap(), Some(now));
+
+        record_attempt(&mut schedule, true, now);
+        assert_eq!(schedule.last_sent, Some(now));
+        assert_eq!(schedule.failed_attempts, 0);
+        assert_eq!(schedule.retry_at, None);
+    }
+
+    #[test]
+    fn failed_digest_is_skipped_after_max_attempts() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap();
+        let mut schedule = schedule();
+        for _ in 0..MAX_ATTEMPTS {
+            record_attempt(&mut schedule, false, now);
+        }
+        assert_eq!(schedule.last_sent, Some(now));
+        assert_eq!(schedule.failed_attempts, 0);
+        assert_eq!(schedule.retry_at, None);
+    }
+}
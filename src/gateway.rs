@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::{env, sync::Arc};
+
+use anyhow::Result;
+use tracing::{debug, info, warn};
+use twilight_gateway::{Event, EventTypeFlags, Intents, Shard, ShardId, StreamExt as _};
+use twilight_model::application::interaction::Interaction;
+use twilight_model::channel::message::component::Container;
+use twilight_model::channel::message::{EmojiReactionType, MessageFlags};
+use twilight_model::gateway::GatewayReaction;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker, UserMarker};
+
+use crate::AppState;
+use crate::card_refs::{self, CardKind, CardRef};
+use crate::emoji::Emojis;
+use crate::interactions::command_handlers::{
+    create_task_from_message, created_task_card, is_created_from, is_dry_run, jump_link,
+};
+use crate::interactions::dispatch::Dispatcher;
+use crate::reminders::reminder_user_id;
+use crate::todoist;
+
+/// The emoji that creates a task from a message if `TASK_REACTION_EMOJI` is not set.
+const DEFAULT_TASK_EMOJI: &str = "📝";
+
+/// The emoji that completes the task created from a message.
+const COMPLETE_EMOJI: &str = "✅";
+
+/// Checks whether tasks can be created and completed by reacting to messages, enabled with
+/// `GATEWAY_ENABLED`.
+pub fn reactions_enabled() -> bool {
+    env::var("GATEWAY_ENABLED").unwrap_or("false".to_string()) == "true"
+}
+
+/// Checks whether interactions are received over the gateway instead of the HTTP interactions
+/// endpoint, with `INTERACTIONS_MODE=gateway`.
+pub fn interactions_over_gateway() -> bool {
+    env::var("INTERACTIONS_MODE").unwrap_or("http".to_string()) == "gateway"
+}
+
+/// Checks whether the bot needs to connect to the gateway.
+pub fn is_enabled() -> bool {
+    reactions_enabled() || interactions_over_gateway()
+}
+
+fn task_emoji() -> String {
+    env::var("TASK_REACTION_EMOJI").unwrap_or_else(|_| DEFAULT_TASK_EMOJI.to_string())
+}
+
+/// Gets the users allowed to create and complete tasks by reacting, from `REACTION_USER_IDS`,
+/// falling back to `REMINDER_USER_ID`.
+fn reaction_user_ids() -> Vec<u64> {
+    match env::var("REACTION_USER_IDS") {
+        Ok(ids) => ids
+            .split(',')
+            .filter_map(|id| id.trim().parse::<u64>().ok())
+            .collect(),
+        Err(_) => reminder_user_id().into_iter().collect(),
+    }
+}
+
+/// Gets the role whose members are allowed to create and complete tasks by reacting, from
+/// `REACTION_ROLE_ID`.
+fn reaction_role_id() -> Option<u64> {
+    env::var("REACTION_ROLE_ID")
+        .ok()
+        .and_then(|id| id.parse::<u64>().ok())
+}
+
+/// Checks whether the user who reacted is allowed to change tasks. Every task goes to the same
+/// Todoist account, so reactions from anyone else are ignored.
+fn is_allowed(reaction: &GatewayReaction) -> bool {
+    reaction_user_ids().contains(&reaction.user_id.get())
+        || reaction_role_id().is_some_and(|role_id| {
+            reaction
+                .member
+                .as_ref()
+                .is_some_and(|member| member.roles.iter().any(|role| role.get() == role_id))
+        })
+}
+
+/// The messages tasks are currently being created from.
+///
+/// Creating a task takes a few seconds, so this stops a second reaction on the same message from
+/// creating another task before the first one exists.
+#[derive(Clone, Default)]
+struct InFlight(Arc<Mutex<HashSet<Id<MessageMarker>>>>);
+
+impl InFlight {
+    /// Claims the message, returning `None` if a task is already being created from it. The
+    /// message is released when the returned guard is dropped.
+    fn claim(&self, message_id: Id<MessageMarker>) -> Option<InFlightGuard> {
+        if !self.0.lock().unwrap().insert(message_id) {
+            return None;
+        }
+        Some(InFlightGuard {
+            in_flight: self.clone(),
+            message_id,
+        })
+    }
+}
+
+struct InFlightGuard {
+    in_flight: InFlight,
+    message_id: Id<MessageMarker>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.0.lock().unwrap().remove(&self.message_id);
+    }
+}
+
+/// Connects to the gateway in the background, handling each event in its own task.
+///
+/// Reading the messages that are reacted to requires the Message Content intent to be enabled for
+/// the bot. Interactions are sent to every connection, so they need no intents.
+pub fn spawn(state: Arc<AppState>, dispatcher: Dispatcher, token: String) {
+    let intents = if reactions_enabled() {
+        Intents::GUILD_MESSAGE_REACTIONS
+            | Intents::DIRECT_MESSAGE_REACTIONS
+            | Intents::MESSAGE_CONTENT
+    } else {
+        Intents::empty()
+    };
+    let mut shard = Shard::new(ShardId::ONE, token, intents);
+    let event_types = EventTypeFlags::REACTION_ADD | EventTypeFlags::INTERACTION_CREATE;
+    let in_flight = InFlight::default();
+
+    tokio::spawn(async move {
+        info!("Connecting to the gateway");
+        while let Some(item) = shard.next_event(event_types).await {
+            let event = match item {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Failed to receive gateway event: {}", e);
+                    continue;
+                }
+            };
+            let state = Arc::clone(&state);
+            let dispatcher = dispatcher.clone();
+            let in_flight = in_flight.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_event(state, &dispatcher, &in_flight, event).await {
+                    warn!("Failed to handle gateway event: {}", e);
+                }
+            });
+        }
+        warn!("Gateway connection closed");
+    });
+}
+
+async fn handle_event(
+    state: Arc<AppState>,
+    dispatcher: &Dispatcher,
+    in_flight: &InFlight,
+    event: Event,
+) -> Result<()> {
+    match event {
+        Event::InteractionCreate(interaction) if interactions_over_gateway() => {
+            handle_interaction(&state, dispatcher, Arc::new(interaction.0)).await
+        }
+        Event::ReactionAdd(reaction) if reactions_enabled() => {
+            handle_reaction(state, in_flight, &reaction).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Dispatches the interaction the same way as the HTTP interactions endpoint, sending the response
+/// with the interaction callback endpoint.
+///
+/// A deferred handler's response is only sent once the deferral has been sent, as Discord rejects
+/// edits to a response it has not received yet.
+async fn handle_interaction(
+    state: &AppState,
+    dispatcher: &Dispatcher,
+    interaction: Arc<Interaction>,
+) -> Result<()> {
+    debug!("Received interaction {} over the gateway", interaction.id);
+    let mut dispatched = dispatcher.dispatch(Arc::clone(&interaction)).await?;
+    state
+        .client
+        .interaction(state.app_id)
+        .create_response(interaction.id, &interaction.token, &dispatched.response)
+        .await?;
+    dispatched.acknowledge();
+    Ok(())
+}
+
+/// Creates a task from the message when it is reacted to with the task emoji, and completes the
+/// task created from it when it is reacted to with ✅.
+async fn handle_reaction(
+    state: Arc<AppState>,
+    in_flight: &InFlight,
+    reaction: &GatewayReaction,
+) -> Result<()> {
+    let is_bot = reaction.user_id.get() == state.app_id.get()
+        || reaction
+            .member
+            .as_ref()
+            .is_some_and(|member| member.user.bot);
+    if is_bot {
+        return Ok(());
+    }
+    let EmojiReactionType::Unicode { name } = &reaction.emoji else {
+        return Ok(());
+    };
+    let is_tracked_emoji = *name == task_emoji() || name == COMPLETE_EMOJI;
+    if is_tracked_emoji && !is_allowed(reaction) {
+        debug!(
+            "Ignoring reaction from user {}, who is not allowed to change tasks",
+            reaction.user_id
+        );
+        return Ok(());
+    }
+
+    if *name == task_emoji() {
+        create_task(&state, in_flight, reaction).await
+    } else if name == COMPLETE_EMOJI {
+        complete_tasks(state, reaction).await
+    } else {
+        Ok(())
+    }
+}
+
+/// Creates a task from the message through the same pipeline as "Add To-Do", sending its card to
+/// the user who reacted in a direct message.
+async fn create_task(
+    state: &AppState,
+    in_flight: &InFlight,
+    reaction: &GatewayReaction,
+) -> Result<()> {
+    // Held until the task exists and the cache is invalidated, so the check below sees it
+    let Some(_claim) = in_flight.claim(reaction.message_id) else {
+        debug!(
+            "A task is already being created from message {}",
+            reaction.message_id
+        );
+        return Ok(());
+    };
+    let link = jump_link(reaction.guild_id, reaction.channel_id, reaction.message_id);
+    let tasks = state.todoist_cache.open_tasks().await?;
+    if let Some(task) = tasks.iter().find(|task| is_created_from(task, &link)) {
+        debug!(
+            "Message {} is already tracked by task {}",
+            reaction.message_id, task.id
+        );
+        return Ok(());
+    }
+    if is_dry_run() {
+        debug!("Dry run enabled, not creating task in Todoist.");
+        return Ok(());
+    }
+
+    let message = state
+        .client
+        .message(reaction.channel_id, reaction.message_id)
+        .await?
+        .model()
+        .await?;
+    let created = create_task_from_message(state, reaction.guild_id, &message).await?;
+    info!(
+        "Created task {} from a reaction to message {}",
+        created.task.id, message.id
+    );
+
+    let container = created_task_card(
+        state,
+        &created.task,
+        &created.subtasks,
+        created.attachment_summary,
+        None,
+    )
+    .await?;
+    let card = send_direct_message(state, reaction.user_id, container).await?;
+    card_refs::record(state, &created.task.id, card).await
+}
+
+/// Completes the open tasks created from the message, letting the user who reacted know in a
+/// direct message.
+async fn complete_tasks(state: Arc<AppState>, reaction: &GatewayReaction) -> Result<()> {
+    let link = jump_link(reaction.guild_id, reaction.channel_id, reaction.message_id);
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let linked = tasks
+        .iter()
+        .filter(|task| is_created_from(task, &link))
+        .collect::<Vec<_>>();
+    if linked.is_empty() {
+        return Ok(());
+    }
+
+    let mut content = String::new();
+    for task in linked {
+        todoist::close_task(&state.todoist_client, &task.id).await?;
+        info!(
+            "Completed task {} from a reaction to message {}",
+            task.id, reaction.message_id
+        );
+        card_refs::spawn_refresh(Arc::clone(&state), task.id.clone(), None);
+        content.push_str(&format!(
+            "{} Completed [{}]({})\n",
+            Emojis::GREEN_TICK,
+            task.content,
+            task.get_url()
+        ));
+    }
+    state.todoist_cache.invalidate_tasks().await;
+
+    let channel_id = direct_message_channel(&state, reaction.user_id).await?;
+    state
+        .client
+        .create_message(channel_id)
+        .content(&content)
+        .await?;
+    Ok(())
+}
+
+async fn direct_message_channel(
+    state: &AppState,
+    user_id: Id<UserMarker>,
+) -> Result<Id<ChannelMarker>> {
+    Ok(state
+        .client
+        .create_private_channel(user_id)
+        .await?
+        .model()
+        .await?
+        .id)
+}
+
+/// Sends the container to the user in a direct message, returning a reference to the message.
+async fn send_direct_message(
+    state: &AppState,
+    user_id: Id<UserMarker>,
+    container: Container,
+) -> Result<CardRef> {
+    let channel_id = direct_message_channel(state, user_id).await?;
+    let message = state
+        .client
+        .create_message(channel_id)
+        .components(&[container.into()])
+        .flags(MessageFlags::IS_COMPONENTS_V2)
+        .await?
+        .model()
+        .await?;
+    Ok(CardRef::Message {
+        channel_id: message.channel_id.get(),
+        message_id: message.id.get(),
+        kind: CardKind::Task,
+    })
+}
@@ -0,0 +1,294 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+use twilight_model::application::command::{
+    Command, CommandOption, CommandOptionChoice, CommandOptionChoiceValue, CommandOptionType,
+};
+use twilight_model::application::interaction::application_command::{
+    CommandDataOption, CommandOptionValue,
+};
+
+use crate::AppState;
+use crate::interactions::truncate;
+use crate::todoist::tree::{PATH_SEPARATOR, ProjectTree};
+
+/// The maximum number of choices Discord accepts in an autocomplete response.
+const MAX_CHOICES: usize = 25;
+
+/// The maximum length of a choice's name.
+const MAX_CHOICE_NAME_LENGTH: usize = 100;
+
+/// How long a provider has to respond. Discord requires a response within 3 seconds.
+const AUTOCOMPLETE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+pub type AutocompleteFuture =
+    Pin<Box<dyn Future<Output = Result<Vec<CommandOptionChoice>>> + Send>>;
+type AutocompleteHandler<S> = Arc<dyn Fn(String, Arc<S>) -> AutocompleteFuture + Send + Sync>;
+
+/// Autocomplete providers keyed by command path and option name.
+pub struct AutocompleteHandlers<S> {
+    handlers: HashMap<(String, String), AutocompleteHandler<S>>,
+}
+
+impl<S> Default for AutocompleteHandlers<S> {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+}
+
+impl<S> AutocompleteHandlers<S>
+where
+    S: Send + Sync + 'static,
+{
+    /// Registers a provider for an option of the command with the provided path (e.g. `label add`).
+    pub fn register<F, Fut>(&mut self, command_path: &str, option: &str, handler: F)
+    where
+        F: Fn(String, Arc<S>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<CommandOptionChoice>>> + Send + 'static,
+    {
+        self.handlers.insert(
+            (command_path.to_string(), option.to_string()),
+            Arc::new(move |value, state| Box::pin(handler(value, state))),
+        );
+    }
+
+    /// Gets the choices for the focused option. Returns no choices if there is no provider for the
+    /// option, or if the provider fails or does not respond in time.
+    pub async fn execute(
+        &self,
+        command_path: &str,
+        option: &str,
+        value: &str,
+        state: Arc<S>,
+    ) -> Vec<CommandOptionChoice> {
+        debug!(
+            "Autocompleting option {} of command {} with value {:?}",
+            option, command_path, value
+        );
+        let Some(handler) = self
+            .handlers
+            .get(&(command_path.to_string(), option.to_string()))
+        else {
+            warn!(
+                "No autocomplete provider for option {} of command {}",
+                option, command_path
+            );
+            return Vec::new();
+        };
+
+        match timeout(AUTOCOMPLETE_TIMEOUT, handler(value.to_string(), state)).await {
+            Ok(Ok(choices)) => choices.into_iter().take(MAX_CHOICES).collect(),
+            Ok(Err(e)) => {
+                warn!(
+                    "Autocomplete provider for option {} of command {} failed: {}",
+                    option, command_path, e
+                );
+                Vec::new()
+            }
+            Err(_) => {
+                warn!(
+                    "Autocomplete provider for option {} of command {} timed out",
+                    option, command_path
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Enables autocomplete on every option that has a registered provider.
+    pub fn enable_autocomplete(&self, commands: &mut [Command]) {
+        for command in commands {
+            self.mark_options(&command.name, &mut command.options);
+        }
+    }
+
+    fn mark_options(&self, path: &str, options: &mut [CommandOption]) {
+        for option in options {
+            match option.kind {
+                CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup => {
+                    let path = format!("{} {}", path, option.name);
+                    if let Some(options) = option.options.as_mut() {
+                        self.mark_options(&path, options);
+                    }
+                }
+                _ => {
+                    if self
+                        .handlers
+                        .contains_key(&(path.to_string(), option.name.clone()))
+                    {
+                        option.autocomplete = Some(true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Gets the name and current value of the focused option.
+pub fn get_focused_option(options: &[CommandDataOption]) -> Option<(&str, &str)> {
+    options.iter().find_map(|option| match &option.value {
+        CommandOptionValue::Focused(value, _) => Some((option.name.as_str(), value.as_str())),
+        _ => None,
+    })
+}
+
+/// Suggests projects by their full path, using the project ID as the value.
+pub async fn projects(value: String, state: Arc<AppState>) -> Result<Vec<CommandOptionChoice>> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    Ok(rank(&value, tree.iter(), |entry| entry.path.clone())
+        .into_iter()
+        .map(|(name, entry)| string_choice(&name, &entry.project.id))
+        .collect())
+}
+
+/// Suggests sections of all projects as "Project / Section" paths, using the section ID as the value.
+pub async fn sections(value: String, state: Arc<AppState>) -> Result<Vec<CommandOptionChoice>> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    let sections = state.todoist_cache.sections().await?;
+    let sections = sections
+        .iter()
+        .filter(|section| !section.is_archived && !section.is_deleted)
+        .filter_map(|section| {
+            tree.path(&section.project_id).map(|path| {
+                (
+                    format!("{}{}{}", path, PATH_SEPARATOR, section.name),
+                    section,
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(rank(&value, sections.iter(), |(name, _)| name.clone())
+        .into_iter()
+        .map(|(name, (_, section))| string_choice(&name, &section.id))
+        .collect())
+}
+
+/// Suggests labels, using the label name as the value.
+pub async fn labels(value: String, state: Arc<AppState>) -> Result<Vec<CommandOptionChoice>> {
+    let labels = state.todoist_cache.labels().await?;
+    Ok(rank(&value, labels.iter(), |label| label.name.clone())
+        .into_iter()
+        .map(|(name, label)| string_choice(&name, &label.name))
+        .collect())
+}
+
+/// Suggests open tasks, using the task ID as the value.
+pub async fn open_tasks(value: String, state: Arc<AppState>) -> Result<Vec<CommandOptionChoice>> {
+    let tasks = state.todoist_cache.open_tasks().await?;
+    Ok(rank(&value, tasks.iter(), |task| task.content.clone())
+        .into_iter()
+        .map(|(name, task)| string_choice(&name, &task.id))
+        .collect())
+}
+
+/// Suggests IANA timezones, using the timezone name as the value.
+pub async fn timezones(value: String, _state: Arc<AppState>) -> Result<Vec<CommandOptionChoice>> {
+    Ok(rank(&value, chrono_tz::TZ_VARIANTS.iter(), |tz| {
+        tz.name().to_string()
+    })
+    .into_iter()
+    .map(|(name, _)| string_choice(&name, &name))
+    .collect())
+}
+
+/// Ranks the items by how well their name matches the query, returning the best matches along
+/// with their names.
+fn rank<'a, T>(
+    query: &str,
+    items: impl Iterator<Item = &'a T>,
+    name: impl Fn(&T) -> String,
+) -> Vec<(String, &'a T)>
+where
+    T: 'a,
+{
+    let mut matches = items
+        .filter_map(|item| {
+            let name = name(item);
+            fuzzy_score(query, &name).map(|score| (score, name, item))
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a, _, _), (b, _, _)| b.cmp(a));
+    matches
+        .into_iter()
+        .take(MAX_CHOICES)
+        .map(|(_, name, item)| (name, item))
+        .collect()
+}
+
+/// Builds a string choice, truncating the name to the length Discord accepts.
+pub fn string_choice(name: &str, value: &str) -> CommandOptionChoice {
+    CommandOptionChoice {
+        name: truncate(name, MAX_CHOICE_NAME_LENGTH),
+        name_localizations: None,
+        value: CommandOptionChoiceValue::String(value.to_string()),
+    }
+}
+
+/// Scores how well the query matches the candidate, ignoring case. Returns `None` if the
+/// characters of the query do not appear in order in the candidate.
+///
+/// Substring matches score higher than scattered matches, and earlier matches score higher than
+/// later ones.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query = query.trim().to_lowercase();
+    let candidate = candidate.to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    if let Some(position) = candidate.find(&query) {
+        return Some(1000 - position as i64);
+    }
+
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut candidate_chars = candidate.chars().enumerate();
+    for query_char in query.chars() {
+        let (index, _) = candidate_chars.find(|(_, c)| *c == query_char)?;
+        score += match last_match {
+            // Reward consecutive characters
+            Some(last) if last + 1 == index => 10,
+            _ => 1,
+        };
+        last_match = Some(index);
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_substring() {
+        assert_eq!(fuzzy_score("milk", "Buy milk"), Some(996));
+        assert_eq!(fuzzy_score("MILK", "buy milk"), Some(996));
+        assert!(fuzzy_score("buy", "Buy milk") > fuzzy_score("milk", "Buy milk"));
+    }
+
+    #[test]
+    fn fuzzy_score_subsequence() {
+        assert_eq!(fuzzy_score("bmi", "Buy milk"), Some(12));
+        // Substring matches always beat scattered matches
+        assert!(fuzzy_score("uy", "Buy milk") > fuzzy_score("bmi", "Buy milk"));
+    }
+
+    #[test]
+    fn fuzzy_score_no_match() {
+        assert_eq!(fuzzy_score("eggs", "Buy milk"), None);
+        // Characters must appear in order
+        assert_eq!(fuzzy_score("kb", "Buy milk"), None);
+    }
+
+    #[test]
+    fn fuzzy_score_empty_query() {
+        assert_eq!(fuzzy_score("", "Buy milk"), Some(0));
+        assert_eq!(fuzzy_score("  ", "Buy milk"), Some(0));
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, FixedOffset};
+use twilight_model::channel::message::EmojiReactionType;
+use twilight_model::channel::message::component::{ButtonStyle, Container, SeparatorSpacingSize};
+use twilight_util::builder::message::{
+    ActionRowBuilder, ButtonBuilder, ContainerBuilder, SectionBuilder, SeparatorBuilder,
+    TextDisplayBuilder,
+};
+
+use crate::AppState;
+use crate::interactions::component_handlers::{ProjectViewPage, SectionTasks};
+use crate::interactions::components::CustomId;
+use crate::interactions::picker::NO_SECTION;
+use crate::todoist::http::models::{Due, Task};
+use crate::todoist::tree::ProjectTree;
+
+/// The number of sections shown on each page of a project.
+const SECTIONS_PER_PAGE: usize = 5;
+
+/// The number of upcoming tasks previewed for each section of a project.
+const TASKS_PER_SECTION: usize = 3;
+
+/// The number of tasks shown on each page of a section's task list.
+const TASKS_PER_PAGE: usize = 10;
+
+/// Builds the overview of a project, listing its sections with their open task counts and next
+/// few tasks.
+pub async fn project_view(state: &AppState, project_id: &str, page: usize) -> Result<Container> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    let project = tree
+        .get(project_id)
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+    let sections = state.todoist_cache.sections().await?;
+    let tasks = state.todoist_cache.open_tasks().await?;
+
+    let mut sections = sections
+        .iter()
+        .filter(|section| {
+            section.project_id == project_id && !section.is_archived && !section.is_deleted
+        })
+        .collect::<Vec<_>>();
+    sections.sort_by_key(|section| section.section_order);
+
+    // Tasks without a section are listed first, as they are in Todoist
+    let groups = std::iter::once((NO_SECTION, "No section"))
+        .chain(
+            sections
+                .iter()
+                .map(|section| (section.id.as_str(), section.name.as_str())),
+        )
+        .map(|(section_id, name)| {
+            (
+                section_id,
+                name,
+                section_tasks(&tasks, project_id, section_id),
+            )
+        })
+        .filter(|(section_id, _, tasks)| *section_id != NO_SECTION || !tasks.is_empty())
+        .collect::<Vec<_>>();
+    let total_tasks = groups
+        .iter()
+        .map(|(_, _, tasks)| tasks.len())
+        .sum::<usize>();
+
+    let pages = groups.len().div_ceil(SECTIONS_PER_PAGE).max(1);
+    let page = page.min(pages - 1);
+
+    let view_project = ButtonBuilder::new(ButtonStyle::Link)
+        .label("View Project")
+        .url(project.project.get_url())
+        .emoji(EmojiReactionType::Unicode {
+            name: "🔗".to_string(),
+        })
+        .build();
+    let mut container = ContainerBuilder::new().component(
+        SectionBuilder::new(view_project)
+            .component(
+                TextDisplayBuilder::new(format!(
+                    "## {}\n**{}** open tasks in **{}** sections{}",
+                    project.path,
+                    total_tasks,
+                    sections.len(),
+                    page_suffix(page, pages)
+                ))
+                .build(),
+            )
+            .build(),
+    );
+
+    if groups.is_empty() {
+        container = container
+            .component(TextDisplayBuilder::new("This project is empty.".to_string()).build());
+    }
+
+    for (section_id, name, tasks) in groups
+        .iter()
+        .skip(page * SECTIONS_PER_PAGE)
+        .take(SECTIONS_PER_PAGE)
+    {
+        let mut content = format!("### {}\n{} open tasks\n", name, tasks.len());
+        for task in tasks.iter().take(TASKS_PER_SECTION) {
+            content.push_str(&format!("- {}\n", task_line(task)));
+        }
+        if tasks.len() > TASKS_PER_SECTION {
+            content.push_str(&format!(
+                "*…and {} more*\n",
+                tasks.len() - TASKS_PER_SECTION
+            ));
+        }
+
+        let view_tasks = ButtonBuilder::new(ButtonStyle::Secondary)
+            .label("Tasks")
+            .custom_id(
+                SectionTasks {
+                    project_id: project_id.to_string(),
+                    section_id: section_id.to_string(),
+                    page: 0,
+                }
+                .to_custom_id()?,
+            )
+            .disabled(tasks.is_empty())
+            .build();
+        container = container.component(
+            SectionBuilder::new(view_tasks)
+                .component(TextDisplayBuilder::new(content).build())
+                .build(),
+        );
+    }
+
+    if pages > 1 {
+        let previous = ProjectViewPage {
+            project_id: project_id.to_string(),
+            page: page.saturating_sub(1),
+        };
+        let next = ProjectViewPage {
+            project_id: project_id.to_string(),
+            page: page + 1,
+        };
+        container = container
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(
+                ActionRowBuilder::new()
+                    .component(
+                        ButtonBuilder::new(ButtonStyle::Secondary)
+                            .label("◀ Previous")
+                            .custom_id(previous.to_custom_id()?)
+                            .disabled(page == 0)
+                            .build(),
+                    )
+                    .component(
+                        ButtonBuilder::new(ButtonStyle::Secondary)
+                            .label("Next ▶")
+                            .custom_id(next.to_custom_id()?)
+                            .disabled(page + 1 >= pages)
+                            .build(),
+                    )
+                    .build(),
+            );
+    }
+
+    Ok(container.build())
+}
+
+/// Builds the list of open tasks in a section of a project, or the tasks without a section if
+/// `section_id` is [`NO_SECTION`].
+pub async fn section_view(
+    state: &AppState,
+    project_id: &str,
+    section_id: &str,
+    page: usize,
+) -> Result<Container> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    let path = tree
+        .path(project_id)
+        .ok_or_else(|| anyhow!("Project {} not found", project_id))?;
+    let sections = state.todoist_cache.sections().await?;
+    let section_name = if section_id == NO_SECTION {
+        "No section"
+    } else {
+        sections
+            .iter()
+            .find(|section| section.id == section_id)
+            .map(|section| section.name.as_str())
+            .ok_or_else(|| anyhow!("Section {} not found", section_id))?
+    };
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let tasks = section_tasks(&tasks, project_id, section_id);
+
+    let pages = tasks.len().div_ceil(TASKS_PER_PAGE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut content = format!(
+        "## {} / {}\n**{}** open tasks{}\n",
+        path,
+        section_name,
+        tasks.len(),
+        page_suffix(page, pages)
+    );
+    if tasks.is_empty() {
+        content.push_str("There are no open tasks in this section.");
+    }
+    for task in tasks
+        .iter()
+        .skip(page * TASKS_PER_PAGE)
+        .take(TASKS_PER_PAGE)
+    {
+        content.push_str(&format!("- {}\n", task_line(task)));
+    }
+
+    let back = ProjectViewPage {
+        project_id: project_id.to_string(),
+        page: 0,
+    };
+    let previous = SectionTasks {
+        project_id: project_id.to_string(),
+        section_id: section_id.to_string(),
+        page: page.saturating_sub(1),
+    };
+    let next = SectionTasks {
+        project_id: project_id.to_string(),
+        section_id: section_id.to_string(),
+        page: page + 1,
+    };
+
+    let mut buttons = ActionRowBuilder::new().component(
+        ButtonBuilder::new(ButtonStyle::Secondary)
+            .label("Back to project")
+            .custom_id(back.to_custom_id()?)
+            .build(),
+    );
+    if pages > 1 {
+        buttons = buttons
+            .component(
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .label("◀ Previous")
+                    .custom_id(previous.to_custom_id()?)
+                    .disabled(page == 0)
+                    .build(),
+            )
+            .component(
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .label("Next ▶")
+                    .custom_id(next.to_custom_id()?)
+                    .disabled(page + 1 >= pages)
+                    .build(),
+            );
+    }
+
+    Ok(ContainerBuilder::new()
+        .component(TextDisplayBuilder::new(content).build())
+        .component(
+            SeparatorBuilder::new()
+                .divider(true)
+                .spacing(SeparatorSpacingSize::Small)
+                .build(),
+        )
+        .component(buttons.build())
+        .build())
+}
+
+/// Gets the open top level tasks in the section, in their Todoist order.
+pub fn section_tasks<'a>(tasks: &'a [Task], project_id: &str, section_id: &str) -> Vec<&'a Task> {
+    let mut tasks = tasks
+        .iter()
+        .filter(|task| {
+            task.project_id == project_id
+                && task.parent_id.is_none()
+                && task.section_id.as_deref().unwrap_or(NO_SECTION) == section_id
+        })
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|task| task.child_order);
+    tasks
+}
+
+/// Formats the task as a link, followed by its due date if it has one.
+fn task_line(task: &Task) -> String {
+    let mut line = format!("[{}]({})", task.content, task.get_url());
+    if let Some(due) = &task.due
+        && let Ok(due_date) = <Due as TryInto<DateTime<FixedOffset>>>::try_into(due.clone())
+    {
+        let style = if due.is_date_only() { "d" } else { "f" };
+        line.push_str(&format!(" <t:{}:{}>", due_date.timestamp(), style));
+    }
+    line
+}
+
+fn page_suffix(page: usize, pages: usize) -> String {
+    if pages > 1 {
+        format!(" (page {} of {})", page + 1, pages)
+    } else {
+        String::new()
+    }
+}
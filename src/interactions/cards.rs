@@ -0,0 +1,390 @@
+use anyhow::{Result, ensure};
+use chrono::{DateTime, FixedOffset};
+use twilight_model::channel::message::component::{
+    Button, ButtonStyle, Container, SeparatorSpacingSize,
+};
+use twilight_model::channel::message::{Component, EmojiReactionType, MessageFlags};
+use twilight_model::http::interaction::{
+    InteractionResponse, InteractionResponseData, InteractionResponseType,
+};
+use twilight_util::builder::message::{
+    ButtonBuilder, ContainerBuilder, SectionBuilder, SeparatorBuilder, TextDisplayBuilder,
+};
+
+use crate::emoji::Emojis;
+use crate::interactions::MAX_TEXT_DISPLAY_LENGTH;
+use crate::interactions::component_handlers::CompleteTaskButton;
+use crate::interactions::components::CustomId;
+use crate::todoist::http::models::{Due, Task};
+
+/// The maximum number of components in a message, as limited by Discord. Components nested in
+/// containers, sections and action rows count towards the limit.
+const MAX_COMPONENTS: usize = 40;
+
+/// The number of components each subtask on a task card takes: a section with its text and button.
+const SUBTASK_COMPONENTS: usize = 3;
+
+/// The maximum number of tasks listed in each list of the today card.
+const MAX_LISTED_TASKS: usize = 25;
+
+/// The length kept free at the end of each task list for its "…and more" line.
+const OVERFLOW_LINE_LENGTH: usize = 50;
+
+/// Counts the component along with the components nested in it.
+fn count_components(component: &Component) -> usize {
+    1 + match component {
+        Component::ActionRow(row) => row.components.iter().map(count_components).sum(),
+        Component::Container(container) => container.components.iter().map(count_components).sum(),
+        Component::Section(section) => {
+            section
+                .components
+                .iter()
+                .map(count_components)
+                .sum::<usize>()
+                + count_components(&section.accessory)
+        }
+        _ => 0,
+    }
+}
+
+/// Builds the card shown for a task created by the bot.
+///
+/// `notes` are shown below the header, and `picker` contains the action rows used to move the
+/// task to a different project or section. Only as many subtasks are shown as fit in the message
+/// alongside the rest of the card.
+pub fn task_card(
+    task: &Task,
+    subtasks: &[Task],
+    notes: &[String],
+    picker: Vec<Component>,
+) -> Result<Container> {
+    let header = TextDisplayBuilder::new(format!(
+        "{} Created task:\n**{}**",
+        Emojis::GREEN_TICK,
+        task.content
+    ))
+    .build();
+
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(0x00AA00))
+        .component(
+            SectionBuilder::new(view_task_button(task))
+                .component(header)
+                .build(),
+        );
+
+    for note in notes {
+        container = container.component(TextDisplayBuilder::new(note.clone()).build());
+    }
+
+    if !subtasks.is_empty() {
+        // The container, the header section, the notes, the "Subtasks" heading and the picker
+        let used = 1
+            + 3
+            + notes.len()
+            + 1
+            + usize::from(!picker.is_empty())
+            + picker.iter().map(count_components).sum::<usize>();
+        let shown = subtasks
+            .len()
+            .min(MAX_COMPONENTS.saturating_sub(used) / SUBTASK_COMPONENTS);
+        let heading = if shown < subtasks.len() {
+            format!(
+                "**Subtasks**\n-# {} more subtasks are not shown",
+                subtasks.len() - shown
+            )
+        } else {
+            "**Subtasks**".to_string()
+        };
+        container = container.component(TextDisplayBuilder::new(heading).build());
+        for subtask in subtasks.iter().take(shown) {
+            let complete_button = ButtonBuilder::new(ButtonStyle::Secondary)
+                .label("Complete")
+                .custom_id(
+                    CompleteTaskButton {
+                        task_id: subtask.id.clone(),
+                    }
+                    .to_custom_id()?,
+                )
+                .emoji(EmojiReactionType::Unicode {
+                    name: "✅".to_string(),
+                })
+                .build();
+            container = container.component(
+                SectionBuilder::new(complete_button)
+                    .component(TextDisplayBuilder::new(format!("☐ {}", subtask.content)).build())
+                    .build(),
+            );
+        }
+    }
+
+    if !picker.is_empty() {
+        container = container.component(
+            SeparatorBuilder::new()
+                .divider(true)
+                .spacing(SeparatorSpacingSize::Large)
+                .build(),
+        );
+        for row in picker {
+            container = container.component(row);
+        }
+    }
+
+    let container = container.build();
+    let count = 1 + container
+        .components
+        .iter()
+        .map(count_components)
+        .sum::<usize>();
+    ensure!(
+        count <= MAX_COMPONENTS,
+        "The card for task {} has {} components, over the limit of {}",
+        task.id,
+        count,
+        MAX_COMPONENTS
+    );
+    Ok(container)
+}
+
+/// Builds the card that replaces a task's card once it has been completed or deleted, e.g.
+/// "~~Buy milk~~ Completed in Todoist".
+pub fn closed_card(content: &str, status: &str) -> Container {
+    ContainerBuilder::new()
+        .accent_color(Some(0x808080))
+        .component(
+            TextDisplayBuilder::new(format!("~~{}~~\n-# {} in Todoist", content, status)).build(),
+        )
+        .build()
+}
+
+/// Builds the card listing the tasks due today, as shown by `/today` and in digests.
+///
+/// Overdue tasks are listed after today's tasks when there are any. Discord limits the length of
+/// the text in the whole message, so when there are overdue tasks each list gets half of it.
+pub fn today_card(tasks: &[Task], overdue: &[Task]) -> Container {
+    let accent_color = if !overdue.is_empty() {
+        0xAA0000 // Red for overdue tasks
+    } else if tasks.is_empty() {
+        0x00AA00 // Green for no tasks
+    } else {
+        0xAAAA00 // Yellow for tasks due
+    };
+
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(accent_color))
+        .component(if tasks.is_empty() {
+            TextDisplayBuilder::new("You have no more tasks due today!".to_string()).build()
+        } else {
+            let mut content = format!("There are **{}** tasks due today:\n", tasks.len());
+            let max_length = if overdue.is_empty() {
+                MAX_TEXT_DISPLAY_LENGTH
+            } else {
+                MAX_TEXT_DISPLAY_LENGTH / 2
+            };
+            let lines = tasks.iter().map(|task| {
+                let mut task_format = format!("[{}]({})", task.content, task.get_url());
+
+                if let Some(due) = &task.due
+                    && !due.is_date_only()
+                    && let Ok(due_date) =
+                        <Due as TryInto<DateTime<FixedOffset>>>::try_into(due.clone())
+                {
+                    let due_unix_time = due_date.timestamp();
+                    task_format.push_str(&format!(" <t:{}:t>", due_unix_time));
+                }
+
+                format!("- {}\n", task_format)
+            });
+            push_task_lines(&mut content, lines, tasks.len(), max_length);
+            TextDisplayBuilder::new(content).build()
+        });
+
+    if !overdue.is_empty() {
+        let mut content = format!("**{}** tasks are overdue:\n", overdue.len());
+        let lines = overdue.iter().map(|task| {
+            let mut task_format = format!("[{}]({})", task.content, task.get_url());
+            if let Some(due) = &task.due
+                && let Ok(due_date) = <Due as TryInto<DateTime<FixedOffset>>>::try_into(due.clone())
+            {
+                task_format.push_str(&format!(" (due <t:{}:R>)", due_date.timestamp()));
+            }
+            format!("- {}\n", task_format)
+        });
+        push_task_lines(
+            &mut content,
+            lines,
+            overdue.len(),
+            MAX_TEXT_DISPLAY_LENGTH / 2,
+        );
+        container = container
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(TextDisplayBuilder::new(content).build());
+    }
+
+    container.build()
+}
+
+/// Appends the lines to the content, stopping after [`MAX_LISTED_TASKS`] or once the content would
+/// be longer than `max_length` characters, and says how many of the `total` tasks were left out.
+fn push_task_lines(
+    content: &mut String,
+    lines: impl Iterator<Item = String>,
+    total: usize,
+    max_length: usize,
+) {
+    let mut length = content.chars().count() + OVERFLOW_LINE_LENGTH;
+    let mut listed = 0;
+    for line in lines.take(MAX_LISTED_TASKS) {
+        length += line.chars().count();
+        if length > max_length {
+            break;
+        }
+        content.push_str(&line);
+        listed += 1;
+    }
+    if total > listed {
+        content.push_str(&format!("-# …and {} more\n", total - listed));
+    }
+}
+
+/// Builds the card asking which task a message should be added to as a comment.
+pub fn comment_picker_card(picker: Vec<Component>) -> Container {
+    let mut container = ContainerBuilder::new().component(
+        TextDisplayBuilder::new(
+            "Which task should this message be added to as a comment?\n\
+            -# The most recently added tasks are listed first. Not listed? Search for it with \
+            `/comment`."
+                .to_string(),
+        )
+        .build(),
+    );
+    for row in picker {
+        container = container.component(row);
+    }
+    container.build()
+}
+
+/// Builds the card confirming the message was added to the task as a comment.
+pub fn comment_added_card(task: &Task) -> Container {
+    ContainerBuilder::new()
+        .accent_color(Some(0x00AA00))
+        .component(
+            SectionBuilder::new(view_task_button(task))
+                .component(
+                    TextDisplayBuilder::new(format!(
+                        "{} Added the message as a comment to:\n**{}**",
+                        Emojis::GREEN_TICK,
+                        task.content
+                    ))
+                    .build(),
+                )
+                .build(),
+        )
+        .build()
+}
+
+pub fn view_task_button(task: &Task) -> Button {
+    ButtonBuilder::new(ButtonStyle::Link)
+        .label("View Task")
+        .url(task.get_url())
+        .emoji(EmojiReactionType::Unicode {
+            name: "🔗".to_string(),
+        })
+        .build()
+}
+
+/// Responds with the container in a new ephemeral message.
+pub fn ephemeral_card(container: Container) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            components: Some(vec![container.into()]),
+            flags: Some(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Responds with a plain text ephemeral message.
+pub fn ephemeral_message(content: String) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some(content),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Responds by replacing the message the component is attached to with the container.
+pub fn update_card(container: Container) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(InteractionResponseData {
+            components: Some(vec![container.into()]),
+            flags: Some(MessageFlags::IS_COMPONENTS_V2),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the length of the text in the container's text displays.
+    fn text_length(container: &Container) -> usize {
+        container
+            .components
+            .iter()
+            .map(|component| match component {
+                Component::TextDisplay(text) => text.content.chars().count(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn tasks(count: usize, content: &str) -> Vec<Task> {
+        (0..count)
+            .map(|i| Task::for_test(&i.to_string(), content))
+            .collect()
+    }
+
+    #[test]
+    fn today_card_lists_every_task_when_short() {
+        let card = today_card(&tasks(3, "Buy milk"), &[]);
+        let Component::TextDisplay(text) = &card.components[0] else {
+            panic!("Expected a text display");
+        };
+        assert_eq!(text.content.matches("Buy milk").count(), 3);
+        assert!(!text.content.contains("more"));
+    }
+
+    #[test]
+    fn today_card_caps_the_number_of_tasks() {
+        let card = today_card(&tasks(MAX_LISTED_TASKS + 5, "Buy milk"), &[]);
+        let Component::TextDisplay(text) = &card.components[0] else {
+            panic!("Expected a text display");
+        };
+        assert_eq!(text.content.matches("Buy milk").count(), MAX_LISTED_TASKS);
+        assert!(text.content.ends_with("-# …and 5 more\n"));
+    }
+
+    #[test]
+    fn today_card_fits_in_a_message() {
+        let long_name = "A very long task name ".repeat(10);
+        let card = today_card(&tasks(100, &long_name), &tasks(100, &long_name));
+        assert!(text_length(&card) <= MAX_TEXT_DISPLAY_LENGTH);
+        for component in &card.components {
+            if let Component::TextDisplay(text) = component {
+                assert!(text.content.contains("more"));
+            }
+        }
+    }
+}
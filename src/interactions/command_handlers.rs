@@ -1,117 +1,461 @@
 use anyhow::Result;
-use futures::future;
 use std::sync::Arc;
+use twilight_model::channel::Attachment;
 use twilight_model::channel::Message;
 use twilight_model::channel::message::Component;
 use twilight_model::channel::message::component::Container;
 use twilight_model::channel::message::component::Section;
-use twilight_model::channel::message::component::SelectMenuType;
 use twilight_util::builder::message::ActionRowBuilder;
 use twilight_util::builder::message::ButtonBuilder;
 use twilight_util::builder::message::SectionBuilder;
-use twilight_util::builder::message::SelectMenuBuilder;
-use twilight_util::builder::message::SelectMenuOptionBuilder;
 use twilight_util::builder::message::SeparatorBuilder;
 
 use crate::AppState;
+use crate::announce::{self, TRACKED_EMOJI};
+use crate::board;
+use crate::card_refs::{self, CardRef};
+use crate::default_timezone;
+use crate::digest::{self, DigestSchedule, DigestTarget};
 use crate::emoji::Emojis;
 use crate::get_timezone_override;
+use crate::interactions::autocomplete::fuzzy_score;
+use crate::interactions::browse;
+use crate::interactions::cards::comment_added_card;
+use crate::interactions::cards::comment_picker_card;
+use crate::interactions::cards::ephemeral_card;
+use crate::interactions::cards::ephemeral_message;
+use crate::interactions::cards::task_card;
+use crate::interactions::cards::today_card;
+use crate::interactions::cards::view_task_button;
+use crate::interactions::component_handlers;
+use crate::interactions::component_handlers::AnnounceButton;
+use crate::interactions::component_handlers::ReopenTaskButton;
+use crate::interactions::components::CustomId;
+use crate::interactions::picker;
+use crate::interactions::{MAX_TEXT_DISPLAY_LENGTH, truncate};
+use crate::llm::prompt::ImageInput;
+use crate::llm::prompt::PromptInput;
+use crate::review;
 use crate::todoist;
+use crate::todoist::NewComment;
+use crate::todoist::NewLabel;
 use crate::todoist::NewTask;
-use crate::todoist::http::models::Due;
+use crate::todoist::QuickAddTask;
+use crate::todoist::UpdateTaskBody;
+use crate::todoist::http::models::Comment;
+use crate::todoist::http::models::Project;
+use crate::todoist::http::models::Section as TodoistSection;
+use crate::todoist::http::models::Task;
+use crate::todoist::tree::{PATH_SEPARATOR, ProjectTree};
 use chrono::DateTime;
-use chrono::FixedOffset;
+use chrono::Utc;
+use chrono_tz::Tz;
 use std::env;
-use tracing::debug;
+use tracing::{debug, info, warn};
 use twilight_commands::Command;
 use twilight_model::application::interaction::InteractionData;
 use twilight_model::channel::message::EmojiReactionType;
 use twilight_model::channel::message::component::ButtonStyle;
 use twilight_model::channel::message::component::SeparatorSpacingSize;
-use twilight_model::http::interaction::InteractionResponseData;
-use twilight_model::http::interaction::InteractionResponseType;
+use twilight_model::guild::Permissions;
 use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, MessageMarker};
 use twilight_model::{
-    application::interaction::Interaction, channel::message::MessageFlags,
-    http::interaction::InteractionResponse,
+    application::interaction::Interaction, http::interaction::InteractionResponse,
 };
 use twilight_util::builder::message::ContainerBuilder;
 use twilight_util::builder::message::TextDisplayBuilder;
 
+/// The maximum number of subtasks created for a single reminder.
+const MAX_SUBTASKS: usize = 10;
+
+/// The maximum number of tasks created from a single message with "Add Multiple To-Dos".
+const MAX_REMINDERS: usize = 5;
+
+/// The maximum number of previous messages included as context, as limited by Discord.
+const MAX_CONTEXT_MESSAGES: u16 = 100;
+
+/// The maximum number of image attachments sent to the LLM.
+const MAX_IMAGES: usize = 5;
+
+/// The maximum number of comments shown by `/comments`.
+const MAX_COMMENTS: usize = 10;
+
+/// The maximum length of a comment shown by `/comments`, so one long comment does not hide the rest.
+const MAX_COMMENT_LENGTH: usize = 1000;
+
+/// The maximum number of tasks suggested when a task option does not match exactly one task.
+const MAX_TASK_CANDIDATES: usize = 5;
+
+/// The start of the description of tasks created from a Discord message, used to recognise them.
+pub const CREATED_FROM_MESSAGE_PREFIX: &str = "Created from message:";
+
+/// The default maximum size of an attachment uploaded to Todoist, in megabytes.
+const DEFAULT_MAX_ATTACHMENT_SIZE_MB: u64 = 5;
+
 pub async fn add_reminder(
     interaction: Arc<Interaction>,
     state: Arc<AppState>,
 ) -> Result<InteractionResponse> {
-    let target_message = {
-        if let Some(InteractionData::ApplicationCommand(c)) = interaction.data.as_ref()
-            && let Some(resolved) = c.resolved.as_ref()
-            && let Some(target) = c.target_id
-        {
-            resolved.messages.get(&Id::new(target.get()))
-        } else {
-            None
-        }
+    let Some(target_message) = get_target_message(&interaction) else {
+        return Ok(missing_target_message_response());
     };
 
-    if target_message.is_none() {
-        return Ok(InteractionResponse {
-            kind: InteractionResponseType::ChannelMessageWithSource,
-            data: Some(InteractionResponseData {
-                content: Some(format!(
-                    "{} Could not find the target message to create a reminder from.",
-                    Emojis::RED_X
-                )),
-                ..Default::default()
-            }),
-        });
+    if is_dry_run() {
+        let content = build_llm_input(&state, target_message).await;
+        let response = state.llm_provider.generate_reminder(&content).await?;
+        debug!("Dry run enabled, not creating task in Todoist.");
+        return Ok(dry_run_response(&response));
     }
-    let target_message = target_message.unwrap();
-    let content = message_to_string(target_message);
-    debug!("Asking Claude to create reminder from text: {}", content);
 
-    let response = state
-        .llm_provider
-        .generate_reminder(content.as_str())
-        .await?;
+    let created = create_task_from_message(&state, interaction.guild_id, target_message).await?;
+    created_task_response(
+        &state,
+        &interaction,
+        Some(target_message),
+        &created.task,
+        &created.subtasks,
+        created.attachment_summary,
+    )
+    .await
+}
+
+/// A task created from a Discord message.
+pub struct CreatedTask {
+    pub task: Task,
+    pub subtasks: Vec<Task>,
+    /// A summary of the message's attachments that were uploaded to the task.
+    pub attachment_summary: Option<String>,
+}
+
+/// Asks the LLM to turn the message into a task and creates it in Todoist, along with its subtasks
+/// and the message's attachments.
+///
+/// `guild_id` is the guild the message was sent in, used to link back to the message.
+pub async fn create_task_from_message(
+    state: &AppState,
+    guild_id: Option<Id<GuildMarker>>,
+    message: &Message,
+) -> Result<CreatedTask> {
+    let content = build_llm_input(state, message).await;
+    debug!(
+        "Asking the LLM to create a reminder from input: {:?}",
+        content
+    );
+
+    let response = state.llm_provider.generate_reminder(&content).await?;
 
     debug!("LLM response: {:#?}", response);
 
-    if env::var("DRY_RUN").unwrap_or("false".to_string()) == "true" {
-        debug!("Dry run enabled, not creating task in Todoist.");
-        return Ok(InteractionResponse {
-            kind: InteractionResponseType::ChannelMessageWithSource,
-            data: Some(InteractionResponseData {
-                content: Some(format!(
-                    "{} (Dry Run) Created reminder: ```\n{:#?}\n```",
-                    Emojis::GREEN_TICK,
-                    response
-                )),
+    // Create the task
+    let task = todoist::create_task(
+        &state.todoist_client,
+        NewTask {
+            content: response.title,
+            description: task_description(guild_id, Some(message), response.links),
+            due_date: response.due,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    debug!("Created new task in Todoist: {:#?}", task);
+    review::record_created(state, &task.id).await;
+
+    let subtasks = create_subtasks(state, &task, response.subtasks).await?;
+    let attachment_summary = upload_attachments(state, &task, message).await;
+    Ok(CreatedTask {
+        task,
+        subtasks,
+        attachment_summary,
+    })
+}
+
+pub async fn add_comment(
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(target_message) = get_target_message(&interaction) else {
+        return Ok(missing_target_message_response());
+    };
+
+    let picker = picker::comment_task_picker(
+        &state,
+        target_message.channel_id.get(),
+        target_message.id.get(),
+        0,
+    )
+    .await?;
+    state.pending_comments.insert(
+        interaction.author_id().map(|user_id| user_id.get()),
+        target_message.clone(),
+    );
+    Ok(ephemeral_card(comment_picker_card(picker)))
+}
+
+/// Adds the message's content, attachments and a jump link to the task as a comment.
+pub async fn add_message_comment(
+    state: &AppState,
+    interaction: &Interaction,
+    message: &Message,
+    task: &Task,
+) -> Result<Comment> {
+    let mut content = message_to_string(message).trim().to_string();
+    if !message.attachments.is_empty() {
+        content.push_str("\n\nAttachments:\n");
+        for attachment in &message.attachments {
+            content.push_str(&format!(
+                "- [{}]({})\n",
+                attachment.filename, attachment.url
+            ));
+        }
+    }
+    content.push_str(&format!(
+        "\n\nFrom [{}'s message]({})",
+        message.author.name,
+        message_link(interaction.guild_id, message)
+    ));
+
+    todoist::create_comment(
+        &state.todoist_client,
+        NewComment {
+            content: content.trim_start().to_string(),
+            task_id: Some(task.id.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+pub async fn add_multiple_reminders(
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(target_message) = get_target_message(&interaction) else {
+        return Ok(missing_target_message_response());
+    };
+    let content = build_llm_input(&state, target_message).await;
+    debug!(
+        "Asking the LLM to create reminders from input: {:?}",
+        content
+    );
+
+    let responses = state.llm_provider.generate_reminders(&content).await?;
+
+    debug!("LLM response: {:#?}", responses);
+
+    if is_dry_run() {
+        debug!("Dry run enabled, not creating tasks in Todoist.");
+        return Ok(ephemeral_message(format!(
+            "{} (Dry Run) Created reminders: ```\n{:#?}\n```",
+            Emojis::GREEN_TICK,
+            responses
+        )));
+    }
+
+    if responses.is_empty() {
+        return Ok(ephemeral_message(format!(
+            "{} Could not find any action items in the message.",
+            Emojis::RED_X
+        )));
+    }
+
+    let mut new_tasks = Vec::new();
+    for response in responses.into_iter().take(MAX_REMINDERS) {
+        let new_task = todoist::create_task(
+            &state.todoist_client,
+            NewTask {
+                content: response.title,
+                description: task_description(
+                    interaction.guild_id,
+                    Some(target_message),
+                    response.links,
+                ),
+                due_date: response.due,
                 ..Default::default()
-            }),
-        });
+            },
+        )
+        .await?;
+        debug!("Created new task in Todoist: {:#?}", new_task);
+        review::record_created(&state, &new_task.id).await;
+        new_tasks.push(new_task);
     }
 
-    let projects = todoist::get_projects(&state.todoist_client).await?;
-    debug!("Retrieved {} projects from Todoist", projects.len());
-    let projects_with_sections = future::join_all(projects.iter().map(|project| {
-        let client = state.todoist_client.clone();
-        let project_id = project.id.as_str().to_string();
-        async move {
-            let sections = todoist::get_sections(&client, &project_id)
-                .await
-                .unwrap_or(Vec::new());
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(0x00AA00))
+        .component(
+            TextDisplayBuilder::new(format!(
+                "{} Created **{}** tasks:",
+                Emojis::GREEN_TICK,
+                new_tasks.len()
+            ))
+            .build(),
+        );
+
+    for new_task in &new_tasks {
+        container = container
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(
+                SectionBuilder::new(view_task_button(new_task))
+                    .component(TextDisplayBuilder::new(format!("**{}**", new_task.content)).build())
+                    .build(),
+            )
+            .component(picker::task_project_select(&state, &new_task.id).await?);
+    }
+    container = container
+        .component(
+            SeparatorBuilder::new()
+                .divider(true)
+                .spacing(SeparatorSpacingSize::Small)
+                .build(),
+        )
+        .component(announcement(&state, &interaction, target_message).await?);
+
+    Ok(ephemeral_card(container.build()))
+}
+
+/// Uploads the message's attachments to the task as comments if `UPLOAD_ATTACHMENTS` is enabled.
+///
+/// Returns a summary of the uploaded and skipped attachments to show on the card.
+async fn upload_attachments(state: &AppState, task: &Task, message: &Message) -> Option<String> {
+    if message.attachments.is_empty()
+        || env::var("UPLOAD_ATTACHMENTS").unwrap_or("false".to_string()) != "true"
+    {
+        return None;
+    }
+    let max_size_mb = env::var("MAX_ATTACHMENT_SIZE_MB")
+        .ok()
+        .and_then(|size| size.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_ATTACHMENT_SIZE_MB);
+
+    let mut uploaded = 0;
+    let mut skipped = Vec::new();
+    for attachment in &message.attachments {
+        if attachment.size > max_size_mb.saturating_mul(1024 * 1024) {
             debug!(
-                "Retrieved {} sections for project {}",
-                sections.len(),
-                project.name
+                "Skipping attachment {} ({} bytes) as it is too large",
+                attachment.filename, attachment.size
             );
-            (project, sections)
+            skipped.push(format!("{} (over {} MB)", attachment.filename, max_size_mb));
+            continue;
         }
-    }))
-    .await;
 
-    let link_text = response
-        .links
+        match upload_attachment(state, task, attachment).await {
+            Ok(()) => uploaded += 1,
+            Err(e) => {
+                warn!(
+                    "Failed to upload attachment {} to task {}: {}",
+                    attachment.filename, task.id, e
+                );
+                skipped.push(format!("{} (upload failed)", attachment.filename));
+            }
+        }
+    }
+
+    let mut summary = format!("📎 Uploaded **{}** attachment(s) to the task.", uploaded);
+    if !skipped.is_empty() {
+        summary.push_str(&format!(" Skipped: {}", skipped.join(", ")));
+    }
+    Some(summary)
+}
+
+async fn upload_attachment(state: &AppState, task: &Task, attachment: &Attachment) -> Result<()> {
+    // Use a separate client so the Todoist token is not sent to Discord's CDN
+    let data = reqwest::get(&attachment.url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let file = todoist::upload_file(
+        &state.todoist_client,
+        &attachment.filename,
+        attachment.content_type.as_deref(),
+        data.to_vec(),
+    )
+    .await?;
+
+    todoist::create_comment(
+        &state.todoist_client,
+        NewComment {
+            content: attachment.filename.clone(),
+            task_id: Some(task.id.clone()),
+            attachment: Some(file),
+            ..Default::default()
+        },
+    )
+    .await?;
+    debug!(
+        "Uploaded attachment {} to task {}",
+        attachment.filename, task.id
+    );
+    Ok(())
+}
+
+/// Gets the message a message context command was invoked on.
+fn get_target_message(interaction: &Interaction) -> Option<&Message> {
+    if let Some(InteractionData::ApplicationCommand(c)) = interaction.data.as_ref()
+        && let Some(resolved) = c.resolved.as_ref()
+        && let Some(target) = c.target_id
+    {
+        resolved.messages.get(&Id::new(target.get()))
+    } else {
+        None
+    }
+}
+
+fn missing_target_message_response() -> InteractionResponse {
+    ephemeral_message(format!(
+        "{} Could not find the target message to create a reminder from.",
+        Emojis::RED_X
+    ))
+}
+
+pub fn is_dry_run() -> bool {
+    env::var("DRY_RUN").unwrap_or("false".to_string()) == "true"
+}
+
+/// Builds the jump link to the message.
+fn message_link(guild_id: Option<Id<GuildMarker>>, message: &Message) -> String {
+    jump_link(guild_id, message.channel_id, message.id)
+}
+
+/// Builds the link that jumps to a message, e.g. `https://discord.com/channels/1/2/3`.
+pub fn jump_link(
+    guild_id: Option<Id<GuildMarker>>,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> String {
+    format!(
+        "https://discord.com/channels/{}/{}/{}",
+        guild_id
+            .map(|id| id.get().to_string())
+            .unwrap_or("@me".to_string()),
+        channel_id,
+        message_id
+    )
+}
+
+/// Checks whether the task was created from the message with the jump link.
+pub fn is_created_from(task: &Task, link: &str) -> bool {
+    task.description
+        .strip_prefix(CREATED_FROM_MESSAGE_PREFIX)
+        .and_then(|description| description.trim_start().strip_prefix(link))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// Builds the description of a task, linking back to the Discord message it was created from.
+fn task_description(
+    guild_id: Option<Id<GuildMarker>>,
+    message: Option<&Message>,
+    links: Option<Vec<String>>,
+) -> Option<String> {
+    let link_text = links
         .map(|links| {
             links
                 .into_iter()
@@ -121,98 +465,1057 @@ pub async fn add_reminder(
         })
         .map(|links| format!("\n\nRelated Links:\n{}", links));
 
+    let attachment_text = message
+        .filter(|message| !message.attachments.is_empty())
+        .map(|message| {
+            let attachments = message
+                .attachments
+                .iter()
+                .map(|attachment| format!("- [{}]({})", attachment.filename, attachment.url))
+                .collect::<Vec<String>>()
+                .join("\n");
+            format!("\n\nAttachments:\n{}", attachments)
+        });
+
     let mut description = String::new();
-    description.push_str(
-        format!(
-            "Created from message: https://discord.com/channels/{}/{}/{}",
-            interaction
-                .guild_id
-                .map(|id| id.get().to_string())
-                .unwrap_or("@me".to_string()),
-            target_message.channel_id,
-            target_message.id
-        )
-        .as_str(),
-    );
+    if let Some(message) = message {
+        description.push_str(
+            format!(
+                "{} {}",
+                CREATED_FROM_MESSAGE_PREFIX,
+                message_link(guild_id, message)
+            )
+            .as_str(),
+        );
+    }
     if let Some(link_text) = link_text {
         description.push_str(link_text.as_str());
     }
+    if let Some(attachment_text) = attachment_text {
+        description.push_str(attachment_text.as_str());
+    }
+    let description = description.trim_start().to_string();
+    (!description.is_empty()).then_some(description)
+}
 
-    // Create the task
-    let new_task = todoist::create_task(
-        &state.todoist_client,
-        NewTask {
-            content: response.title,
-            description: Some(description),
-            due_date: response.due,
-            ..Default::default()
+#[derive(Command, Debug)]
+#[command(name = "add", description = "Create a task from text")]
+pub struct AddTask {
+    #[option(description = "The text to create the task from")]
+    pub text: String,
+    #[option(description = "The project to add the task to")]
+    pub project: Option<String>,
+    #[option(description = "The section to add the task to")]
+    pub section: Option<String>,
+    #[option(description = "When the task is due, e.g. \"tomorrow at 5pm\"")]
+    pub due: Option<String>,
+    #[option(description = "The priority of the task, from 1 (urgent) to 4 (normal)")]
+    pub priority: Option<i64>,
+    #[option(description = "Parse the text with Todoist's Quick Add syntax instead of the LLM")]
+    pub quick_add: Option<bool>,
+}
+
+pub async fn handle_add(
+    args: AddTask,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let has_explicit_options = args.project.is_some()
+        || args.section.is_some()
+        || args.due.is_some()
+        || args.priority.is_some();
+    // Quick Add only takes the text, so the options would be silently dropped
+    if args.quick_add == Some(true) && has_explicit_options {
+        return Ok(ephemeral_message(format!(
+            "{} `quick_add` can't be combined with `project`, `section`, `due` or `priority`. \
+            Use `#project`, `/section`, a date and `p1`-`p4` in the text instead.",
+            Emojis::RED_X
+        )));
+    }
+
+    let projects = state.todoist_cache.projects().await?;
+
+    let project_id = match &args.project {
+        Some(project) => match find_project(&projects, project) {
+            Some(project) => Some(project.id.clone()),
+            None => {
+                return Ok(ephemeral_message(format!(
+                    "{} Could not find a project named `{}`.",
+                    Emojis::RED_X,
+                    project
+                )));
+            }
         },
-    )
-    .await?;
+        None => None,
+    };
+    let section = match &args.section {
+        Some(query) => {
+            let sections = state.todoist_cache.sections().await?;
+            match find_section(&sections, &projects, query) {
+                Some(section) => Some(section.clone()),
+                None => {
+                    return Ok(ephemeral_message(format!(
+                        "{} Could not find a section named `{}`.",
+                        Emojis::RED_X,
+                        query
+                    )));
+                }
+            }
+        }
+        None => None,
+    };
+    if let (Some(project_id), Some(section)) = (&project_id, &section)
+        && section.project_id != *project_id
+    {
+        return Ok(ephemeral_message(format!(
+            "{} The section **{}** is not in that project.",
+            Emojis::RED_X,
+            section.name
+        )));
+    }
+    // The section's project is used when only the section is given
+    let project_id = project_id.or_else(|| section.as_ref().map(|s| s.project_id.clone()));
+    let section_id = section.map(|section| section.id);
+    // Todoist's API uses 4 for urgent tasks, while the app shows them as p1
+    let priority = args.priority.map(|priority| 5 - priority.clamp(1, 4) as u8);
 
-    debug!("Created new task in Todoist: {:#?}", new_task);
-    let mut section_component = SelectMenuBuilder::new(
-        format!("section_select:{}", new_task.id),
-        SelectMenuType::Text,
-    )
-    .placeholder("Update Section");
+    let quick_add = match args.quick_add {
+        Some(quick_add) => quick_add,
+        None if has_explicit_options => false,
+        None => {
+            let labels = state.todoist_cache.labels().await?;
+            let labels = labels
+                .iter()
+                .map(|label| label.name.as_str())
+                .collect::<Vec<_>>();
+            todoist::is_quick_add_syntax(&args.text, &labels)
+        }
+    };
 
-    for (project, sections) in projects_with_sections.iter() {
-        // let builder = SelectMenuOptionBuilder::new("")
-        section_component = section_component.option(
-            SelectMenuOptionBuilder::new(project.name.clone(), project.id.clone())
-                .description(format!("Add to project: {}", project.name))
-                .build(),
+    // Quick Add and explicit options are taken as-is, skipping the LLM
+    let (new_task, subtasks) = if quick_add {
+        if is_dry_run() {
+            debug!("Dry run enabled, not creating task in Todoist.");
+            return Ok(dry_run_response(&args));
+        }
+
+        debug!("Creating task with Quick Add: {}", args.text);
+        let new_task = todoist::quick_add_task(
+            &state.todoist_client,
+            QuickAddTask {
+                text: args.text,
+                ..Default::default()
+            },
+        )
+        .await?;
+        (new_task, Vec::new())
+    } else if has_explicit_options {
+        if is_dry_run() {
+            debug!("Dry run enabled, not creating task in Todoist.");
+            return Ok(dry_run_response(&args));
+        }
+
+        let new_task = todoist::create_task(
+            &state.todoist_client,
+            NewTask {
+                content: args.text,
+                project_id,
+                section_id,
+                priority,
+                due_string: args.due,
+                ..Default::default()
+            },
+        )
+        .await?;
+        (new_task, Vec::new())
+    } else {
+        let content = PromptInput::from(args.text);
+        debug!(
+            "Asking the LLM to create reminder from input: {:?}",
+            content
         );
-        for section in sections {
-            let option = SelectMenuOptionBuilder::new(
-                format!("{} / {}", project.name, section.name),
-                format!("{}-{}", project.id, section.id),
-            )
-            .description(format!("Add to section: {}", section.name))
-            .build();
-            section_component = section_component.option(option);
+
+        let response = state.llm_provider.generate_reminder(&content).await?;
+        debug!("LLM response: {:#?}", response);
+
+        if is_dry_run() {
+            debug!("Dry run enabled, not creating task in Todoist.");
+            return Ok(dry_run_response(&response));
         }
-    }
 
-    let section_component = section_component.build();
-    let section_component = ActionRowBuilder::new().component(section_component).build();
+        let new_task = todoist::create_task(
+            &state.todoist_client,
+            NewTask {
+                content: response.title,
+                description: task_description(interaction.guild_id, None, response.links),
+                due_date: response.due,
+                ..Default::default()
+            },
+        )
+        .await?;
+        let subtasks = create_subtasks(&state, &new_task, response.subtasks).await?;
+        (new_task, subtasks)
+    };
+
+    debug!("Created new task in Todoist: {:#?}", new_task);
+    review::record_created(&state, &new_task.id).await;
+
+    created_task_response(&state, &interaction, None, &new_task, &subtasks, None).await
+}
 
-    let header = TextDisplayBuilder::new(format!(
-        "{} Created task:\n**{}**",
+/// Creates the subtasks as children of the task, preserving their order.
+async fn create_subtasks(
+    state: &AppState,
+    parent: &Task,
+    subtasks: Option<Vec<String>>,
+) -> Result<Vec<Task>> {
+    let mut created = Vec::new();
+    for subtask in subtasks.unwrap_or_default().into_iter().take(MAX_SUBTASKS) {
+        let subtask = todoist::create_task(
+            &state.todoist_client,
+            NewTask {
+                content: subtask,
+                parent_id: Some(parent.id.clone()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        debug!("Created subtask {} for task {}", subtask.id, parent.id);
+        created.push(subtask);
+    }
+    Ok(created)
+}
+
+fn dry_run_response(reminder: &impl std::fmt::Debug) -> InteractionResponse {
+    ephemeral_message(format!(
+        "{} (Dry Run) Created reminder: ```\n{:#?}\n```",
         Emojis::GREEN_TICK,
-        new_task.content
+        reminder
     ))
-    .build();
+}
+
+/// Finds a project by its ID or by its name, ignoring case.
+fn find_project<'a>(projects: &'a [Project], query: &str) -> Option<&'a Project> {
+    ProjectTree::new(projects)
+        .iter()
+        .find(|entry| {
+            entry.project.id == query
+                || entry.path.eq_ignore_ascii_case(query)
+                || entry.project.name.eq_ignore_ascii_case(query)
+        })
+        .map(|entry| entry.project)
+}
+
+/// Finds a section by its ID, or by its "Project / Section" path or name, ignoring case.
+fn find_section<'a>(
+    sections: &'a [TodoistSection],
+    projects: &[Project],
+    query: &str,
+) -> Option<&'a TodoistSection> {
+    let tree = ProjectTree::new(projects);
+    sections
+        .iter()
+        .filter(|section| !section.is_archived && !section.is_deleted)
+        .find(|section| {
+            section.id == query
+                || section.name.eq_ignore_ascii_case(query)
+                || tree.path(&section.project_id).is_some_and(|path| {
+                    format!("{}{}{}", path, PATH_SEPARATOR, section.name)
+                        .eq_ignore_ascii_case(query)
+                })
+        })
+}
+
+/// Finds the open task with the provided ID.
+///
+/// Autocomplete provides the task ID, but fall back to the task with the name, ignoring case, or
+/// the only task containing it if the user did not pick one of the suggestions. Otherwise, returns
+/// the closest matches so the user can pick one instead of acting on the wrong task.
+fn find_open_task<'a>(tasks: &'a [Task], query: &str) -> Result<&'a Task, Vec<&'a Task>> {
+    if let Some(task) = tasks.iter().find(|task| task.id == query) {
+        return Ok(task);
+    }
+
+    let query = query.trim();
+    let named = tasks
+        .iter()
+        .filter(|task| task.content.eq_ignore_ascii_case(query))
+        .collect::<Vec<_>>();
+    if let [task] = named.as_slice() {
+        return Ok(task);
+    }
+    let lowercase_query = query.to_lowercase();
+    let containing = tasks
+        .iter()
+        .filter(|task| task.content.to_lowercase().contains(&lowercase_query))
+        .collect::<Vec<_>>();
+    if named.is_empty()
+        && let [task] = containing.as_slice()
+    {
+        return Ok(task);
+    }
+
+    let mut candidates = tasks
+        .iter()
+        .filter_map(|task| fuzzy_score(query, &task.content).map(|score| (score, task)))
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    Err(candidates
+        .into_iter()
+        .take(MAX_TASK_CANDIDATES)
+        .map(|(_, task)| task)
+        .collect())
+}
+
+/// Responds with the tasks that could match the query when [`find_open_task`] did not find
+/// exactly one, asking the user to pick one of them.
+fn task_not_found_response(query: &str, candidates: &[&Task]) -> InteractionResponse {
+    if candidates.is_empty() {
+        return ephemeral_message(format!(
+            "{} Could not find an open task matching `{}`.",
+            Emojis::RED_X,
+            query
+        ));
+    }
+
+    let mut content = format!(
+        "{} Could not find a single open task matching `{}`. Did you mean:\n",
+        Emojis::RED_X,
+        query
+    );
+    for task in candidates {
+        content.push_str(&format!("- [{}]({})\n", task.content, task.get_url()));
+    }
+    content.push_str("-# Pick the task from the suggestions shown while typing the command.");
+    ephemeral_message(content)
+}
+
+/// Responds with the "Created task" card after a task is created.
+///
+/// The card is remembered so it can be updated when the task changes. If the task was created
+/// from a `source` message, the card also shows whether the message was announced.
+async fn created_task_response(
+    state: &AppState,
+    interaction: &Interaction,
+    source: Option<&Message>,
+    new_task: &Task,
+    subtasks: &[Task],
+    attachment_summary: Option<String>,
+) -> Result<InteractionResponse> {
+    if let Err(e) = card_refs::record(state, &new_task.id, CardRef::interaction(interaction)).await
+    {
+        warn!("Failed to record card for task {}: {}", new_task.id, e);
+    }
+    let announcement = match source {
+        Some(message) => Some(announcement(state, interaction, message).await?),
+        None => None,
+    };
+    Ok(ephemeral_card(
+        created_task_card(state, new_task, subtasks, attachment_summary, announcement).await?,
+    ))
+}
+
+/// Builds the "Created task" card shown after a task is created, with the project and label
+/// pickers followed by the `extra` component if there is one.
+pub async fn created_task_card(
+    state: &AppState,
+    new_task: &Task,
+    subtasks: &[Task],
+    attachment_summary: Option<String>,
+    extra: Option<Component>,
+) -> Result<Container> {
+    state.todoist_cache.invalidate_tasks().await;
+    let mut picker = picker::project_picker(state, &new_task.id, 0).await?;
+    picker.extend(picker::label_picker(state, new_task).await?);
+    picker.extend(extra);
+    let mut notes = attachment_summary.into_iter().collect::<Vec<_>>();
+    notes.insert(0, component_handlers::task_details(state, new_task).await);
+    task_card(new_task, subtasks, &notes, picker)
+}
+
+/// Announces the message a task was created from if its guild announces tasks by default,
+/// returning a note saying so. Otherwise, returns a button to announce it.
+async fn announcement(
+    state: &AppState,
+    interaction: &Interaction,
+    message: &Message,
+) -> Result<Component> {
+    if announce::is_default(state, interaction.guild_id).await
+        && let Some(user_id) = interaction.author_id()
+    {
+        match announce::announce(state, message.channel_id, message.id, user_id).await {
+            Ok(_) => {
+                return Ok(TextDisplayBuilder::new(format!(
+                    "-# {} Announced in the channel",
+                    TRACKED_EMOJI
+                ))
+                .build()
+                .into());
+            }
+            Err(e) => warn!("Failed to announce message {}: {}", message.id, e),
+        }
+    }
+
+    Ok(ActionRowBuilder::new()
+        .component(
+            ButtonBuilder::new(ButtonStyle::Secondary)
+                .label("Announce")
+                .custom_id(
+                    AnnounceButton {
+                        channel_id: message.channel_id.get(),
+                        message_id: message.id.get(),
+                    }
+                    .to_custom_id()?,
+                )
+                .emoji(EmojiReactionType::Unicode {
+                    name: TRACKED_EMOJI.to_string(),
+                })
+                .build(),
+        )
+        .build()
+        .into())
+}
+
+#[derive(Command)]
+#[command(name = "complete", description = "Complete a task")]
+pub struct CompleteTask {
+    #[option(description = "The task to complete")]
+    pub task: String,
+}
+
+pub async fn handle_complete(
+    args: CompleteTask,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let tasks = state.todoist_cache.open_tasks().await?;
+
+    let task = match find_open_task(&tasks, &args.task) {
+        Ok(task) => task,
+        Err(candidates) => return Ok(task_not_found_response(&args.task, &candidates)),
+    };
+
+    todoist::close_task(&state.todoist_client, &task.id).await?;
+    state.todoist_cache.invalidate_tasks().await;
+    debug!("Completed task {}", task.id);
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), None);
 
-    let accessory = ButtonBuilder::new(ButtonStyle::Link)
-        .label("View Task")
-        .url(new_task.get_url())
+    let undo_button = ButtonBuilder::new(ButtonStyle::Secondary)
+        .label("Undo")
+        .custom_id(
+            ReopenTaskButton {
+                task_id: task.id.clone(),
+            }
+            .to_custom_id()?,
+        )
         .emoji(EmojiReactionType::Unicode {
-            name: "🔗".to_string(),
+            name: "↩️".to_string(),
         })
         .build();
 
     let container = ContainerBuilder::new()
         .accent_color(Some(0x00AA00))
-        .component(SectionBuilder::new(accessory).component(header).build())
         .component(
-            SeparatorBuilder::new()
-                .divider(true)
-                .spacing(SeparatorSpacingSize::Large)
+            SectionBuilder::new(undo_button)
+                .component(
+                    TextDisplayBuilder::new(format!(
+                        "{} Completed task:\n~~{}~~",
+                        Emojis::GREEN_TICK,
+                        task.content
+                    ))
+                    .build(),
+                )
                 .build(),
         )
-        .component(section_component)
         .build();
 
-    Ok(InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(InteractionResponseData {
-            components: Some(vec![container.into()]),
-            flags: Some(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2),
+    Ok(ephemeral_card(container))
+}
+
+#[derive(Command)]
+#[command(name = "label add", description = "Add a label to a task")]
+pub struct AddLabel {
+    #[option(description = "The task to label")]
+    pub task: String,
+    #[option(description = "The label to add, created if it does not exist")]
+    pub label: String,
+}
+
+pub async fn handle_label_add(
+    args: AddLabel,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let label = args.label.trim().trim_start_matches('@').to_string();
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let task = match find_open_task(&tasks, &args.task) {
+        Ok(task) => task,
+        Err(candidates) => return Ok(task_not_found_response(&args.task, &candidates)),
+    };
+    if task.labels.contains(&label) {
+        return Ok(ephemeral_message(format!(
+            "{} **{}** is already labelled with **@{}**.",
+            Emojis::RED_X,
+            task.content,
+            label
+        )));
+    }
+
+    let labels = state.todoist_cache.labels().await?;
+    if !labels.iter().any(|existing| existing.name == label) {
+        todoist::create_label(
+            &state.todoist_client,
+            NewLabel {
+                name: label.clone(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        state.todoist_cache.invalidate_structure().await;
+        debug!("Created label {}", label);
+    }
+
+    let mut task_labels = task.labels.clone();
+    task_labels.push(label.clone());
+    let task = todoist::update_task(
+        &state.todoist_client,
+        &task.id,
+        UpdateTaskBody {
+            labels: Some(task_labels),
             ..Default::default()
-        }),
-    })
+        },
+    )
+    .await?;
+    state.todoist_cache.invalidate_tasks().await;
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), None);
+
+    Ok(ephemeral_message(format!(
+        "{} Added **@{}** to [{}]({}).",
+        Emojis::GREEN_TICK,
+        label,
+        task.content,
+        task.get_url()
+    )))
+}
+
+#[derive(Command)]
+#[command(name = "label remove", description = "Remove a label from a task")]
+pub struct RemoveLabel {
+    #[option(description = "The task to remove the label from")]
+    pub task: String,
+    #[option(description = "The label to remove")]
+    pub label: String,
+}
+
+pub async fn handle_label_remove(
+    args: RemoveLabel,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let label = args.label.trim().trim_start_matches('@');
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let task = match find_open_task(&tasks, &args.task) {
+        Ok(task) => task,
+        Err(candidates) => return Ok(task_not_found_response(&args.task, &candidates)),
+    };
+    if !task.labels.iter().any(|existing| existing == label) {
+        return Ok(ephemeral_message(format!(
+            "{} **{}** is not labelled with **@{}**.",
+            Emojis::RED_X,
+            task.content,
+            label
+        )));
+    }
+
+    let task_labels = task
+        .labels
+        .iter()
+        .filter(|existing| *existing != label)
+        .cloned()
+        .collect();
+    let task = todoist::update_task(
+        &state.todoist_client,
+        &task.id,
+        UpdateTaskBody {
+            labels: Some(task_labels),
+            ..Default::default()
+        },
+    )
+    .await?;
+    state.todoist_cache.invalidate_tasks().await;
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), None);
+
+    Ok(ephemeral_message(format!(
+        "{} Removed **@{}** from [{}]({}).",
+        Emojis::GREEN_TICK,
+        label,
+        task.content,
+        task.get_url()
+    )))
+}
+
+#[derive(Command)]
+#[command(
+    name = "comment",
+    description = "Add the message picked with \"Add as comment to task…\" to a task"
+)]
+pub struct AddCommentToTask {
+    #[option(description = "The task to add the message to")]
+    pub task: String,
+}
+
+/// Adds the message the user most recently picked with "Add as comment to task…" to the task, for
+/// tasks that are not listed in its menu.
+pub async fn handle_comment(
+    args: AddCommentToTask,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(message) = interaction
+        .author_id()
+        .and_then(|user_id| state.pending_comments.latest(user_id.get()))
+    else {
+        return Ok(ephemeral_message(format!(
+            "{} Use \"Add as comment to task…\" on a message first, then pick the task here.",
+            Emojis::RED_X
+        )));
+    };
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let task = match find_open_task(&tasks, &args.task) {
+        Ok(task) => task,
+        Err(candidates) => return Ok(task_not_found_response(&args.task, &candidates)),
+    };
+
+    add_message_comment(&state, &interaction, &message, task).await?;
+    state.pending_comments.remove(message.id.get());
+    info!(
+        "Added message {} as a comment to task {}",
+        message.id, task.id
+    );
+    Ok(ephemeral_card(comment_added_card(task)))
+}
+
+#[derive(Command)]
+#[command(name = "comments", description = "Show the comments on a task")]
+pub struct ViewComments {
+    #[option(description = "The task to show the comments of")]
+    pub task: String,
+}
+
+pub async fn handle_comments(
+    args: ViewComments,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let task = match find_open_task(&tasks, &args.task) {
+        Ok(task) => task,
+        Err(candidates) => return Ok(task_not_found_response(&args.task, &candidates)),
+    };
+
+    let comments = todoist::get_task_comments(&state.todoist_client, &task.id).await?;
+    let comments = comments
+        .iter()
+        .filter(|comment| !comment.is_deleted)
+        .collect::<Vec<_>>();
+
+    let mut container = ContainerBuilder::new().component(
+        SectionBuilder::new(view_task_button(task))
+            .component(
+                TextDisplayBuilder::new(format!(
+                    "## {}\n**{}** comments",
+                    task.content,
+                    comments.len()
+                ))
+                .build(),
+            )
+            .build(),
+    );
+
+    // Show the most recent comments that fit, keeping them in chronological order
+    let mut shown = Vec::new();
+    let mut length = 0;
+    for comment in comments.iter().rev().take(MAX_COMMENTS) {
+        let formatted = format_comment(comment);
+        length += formatted.len();
+        // Leave room for the header, as the limit applies to the text of the whole message
+        if length > MAX_TEXT_DISPLAY_LENGTH - 500 {
+            break;
+        }
+        shown.push(formatted);
+    }
+    if shown.len() < comments.len() {
+        container = container.component(
+            TextDisplayBuilder::new(format!(
+                "-# {} earlier comments are not shown",
+                comments.len() - shown.len()
+            ))
+            .build(),
+        );
+    }
+    for comment in shown.into_iter().rev() {
+        container = container
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(TextDisplayBuilder::new(comment).build());
+    }
+
+    Ok(ephemeral_card(container.build()))
+}
+
+fn format_comment(comment: &Comment) -> String {
+    let mut formatted = match DateTime::parse_from_rfc3339(&comment.posted_at) {
+        Ok(posted_at) => format!("-# <t:{}:f>\n", posted_at.timestamp()),
+        Err(_) => String::new(),
+    };
+    formatted.push_str(&truncate(&comment.content, MAX_COMMENT_LENGTH));
+    if let Some(attachment) = &comment.file_attachment {
+        formatted.push_str(&format!(
+            "\n📎 [{}]({})",
+            attachment.file_name, attachment.file_url
+        ));
+    }
+    formatted
+}
+
+#[derive(Command)]
+#[command(
+    name = "digest schedule",
+    description = "Schedule a digest of the tasks due each day"
+)]
+pub struct ScheduleDigest {
+    #[option(
+        description = "When to send the digest as a cron expression, e.g. \"0 8 * * *\" for 8am"
+    )]
+    pub cron: String,
+    #[option(description = "The timezone to use, defaults to the bot's timezone")]
+    pub timezone: Option<String>,
+    #[option(description = "Post the digest in this channel instead of a direct message")]
+    pub here: Option<bool>,
+}
+
+pub async fn handle_digest_schedule(
+    args: ScheduleDigest,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(user_id) = interaction.author_id() else {
+        return Ok(ephemeral_message(format!(
+            "{} Could not determine who ran the command.",
+            Emojis::RED_X
+        )));
+    };
+    if let Err(e) = digest::parse_schedule(&args.cron) {
+        return Ok(ephemeral_message(format!(
+            "{} `{}` is not a valid cron expression: {}",
+            Emojis::RED_X,
+            args.cron,
+            e
+        )));
+    }
+    let timezone = match &args.timezone {
+        Some(timezone) => match timezone.parse::<Tz>() {
+            Ok(timezone) => timezone,
+            Err(_) => {
+                return Ok(ephemeral_message(format!(
+                    "{} `{}` is not a valid timezone.",
+                    Emojis::RED_X,
+                    timezone
+                )));
+            }
+        },
+        None => default_timezone(),
+    };
+    let target = match (args.here.unwrap_or(false), interaction.channel.as_ref()) {
+        (true, Some(channel)) => DigestTarget::Channel {
+            channel_id: channel.id.get(),
+        },
+        _ => DigestTarget::DirectMessage,
+    };
+
+    let schedule = DigestSchedule {
+        cron: args.cron.trim().to_string(),
+        timezone: timezone.name().to_string(),
+        target,
+        created_at: Utc::now(),
+        last_sent: None,
+        failed_attempts: 0,
+        retry_at: None,
+    };
+    let next_run = schedule.next_run()?;
+    state
+        .digests
+        .update(|digests| digests.insert(user_id.get(), schedule))
+        .await?;
+    debug!("Scheduled digest for user {}", user_id);
+
+    let destination = match target {
+        DigestTarget::DirectMessage => "to you in a direct message".to_string(),
+        DigestTarget::Channel { channel_id } => format!("in <#{}>", channel_id),
+    };
+    let next_run = next_run
+        .map(|next_run| {
+            format!(
+                " The next digest will be sent <t:{}:F>.",
+                next_run.timestamp()
+            )
+        })
+        .unwrap_or_default();
+    Ok(ephemeral_message(format!(
+        "{} Your digest will be sent {} on `{}` ({}).{}",
+        Emojis::GREEN_TICK,
+        destination,
+        args.cron.trim(),
+        timezone.name(),
+        next_run
+    )))
+}
+
+#[derive(Command)]
+#[command(name = "digest disable", description = "Stop sending your digest")]
+pub struct DisableDigest;
+
+pub async fn handle_digest_disable(
+    _args: DisableDigest,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(user_id) = interaction.author_id() else {
+        return Ok(ephemeral_message(format!(
+            "{} Could not determine who ran the command.",
+            Emojis::RED_X
+        )));
+    };
+
+    let removed = state
+        .digests
+        .update(|digests| digests.remove(&user_id.get()))
+        .await?;
+    Ok(ephemeral_message(match removed {
+        Some(_) => format!("{} Your digest has been disabled.", Emojis::GREEN_TICK),
+        None => format!("{} You do not have a digest scheduled.", Emojis::RED_X),
+    }))
+}
+
+#[derive(Command)]
+#[command(
+    name = "digest preview",
+    description = "Show what your digest looks like right now"
+)]
+pub struct PreviewDigest;
+
+pub async fn handle_digest_preview(
+    _args: PreviewDigest,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let timezone = match interaction.author_id() {
+        Some(user_id) => state
+            .digests
+            .read()
+            .await
+            .get(&user_id.get())
+            .and_then(|schedule| schedule.timezone().ok()),
+        None => None,
+    }
+    .unwrap_or_else(default_timezone);
+
+    let container = digest::build_digest(&state, timezone).await?;
+    Ok(ephemeral_card(container))
+}
+
+#[derive(Command)]
+#[command(
+    name = "review week",
+    description = "Summarise the past week and the week ahead"
+)]
+pub struct ReviewWeek;
+
+pub async fn handle_review_week(
+    _args: ReviewWeek,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let container = review::build_review(&state, default_timezone()).await?;
+    Ok(ephemeral_card(container))
+}
+
+#[derive(Command)]
+#[command(
+    name = "settings announce",
+    description = "Choose whether tasks created from messages in this server are announced"
+)]
+pub struct AnnounceSetting {
+    #[option(
+        description = "Mark messages as tracked without asking, only who tracked them is shown"
+    )]
+    pub enabled: bool,
+}
+
+pub async fn handle_settings_announce(
+    args: AnnounceSetting,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(ephemeral_message(format!(
+            "{} This setting can only be changed in a server.",
+            Emojis::RED_X
+        )));
+    };
+    let can_manage_guild = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.contains(Permissions::MANAGE_GUILD));
+    if !can_manage_guild {
+        return Ok(ephemeral_message(format!(
+            "{} You need the Manage Server permission to change this setting.",
+            Emojis::RED_X
+        )));
+    }
+
+    state
+        .announcements
+        .update(|announcements| announcements.insert(guild_id.get(), args.enabled))
+        .await?;
+    debug!(
+        "Set announcement default of guild {} to {}",
+        guild_id, args.enabled
+    );
+    Ok(ephemeral_message(if args.enabled {
+        format!(
+            "{} Messages tasks are created from in this server will be marked with {} and a reply \
+            saying who tracked them.",
+            Emojis::GREEN_TICK,
+            TRACKED_EMOJI
+        )
+    } else {
+        format!(
+            "{} Tasks created from messages in this server will only be announced when the \
+            **Announce** button is used.",
+            Emojis::GREEN_TICK
+        )
+    }))
+}
+
+#[derive(Command)]
+#[command(
+    name = "board create",
+    description = "Post a board of a project's open tasks that keeps itself up to date"
+)]
+pub struct CreateBoard {
+    #[option(description = "The project to show on the board")]
+    pub project: String,
+}
+
+pub async fn handle_board_create(
+    args: CreateBoard,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(channel) = interaction.channel.as_ref() else {
+        return Ok(ephemeral_message(format!(
+            "{} Boards can only be posted in a channel.",
+            Emojis::RED_X
+        )));
+    };
+    let projects = state.todoist_cache.projects().await?;
+    let Some(project) = find_project(&projects, &args.project) else {
+        return Ok(ephemeral_message(format!(
+            "{} Could not find a project named `{}`.",
+            Emojis::RED_X,
+            args.project
+        )));
+    };
+
+    board::post_board(&state, channel.id.get(), &project.id).await?;
+    Ok(ephemeral_message(format!(
+        "{} Posted a board for **{}**. It will be kept up to date until it is removed with \
+        `/board delete`.",
+        Emojis::GREEN_TICK,
+        project.name
+    )))
+}
+
+#[derive(Command)]
+#[command(
+    name = "board delete",
+    description = "Remove a project's board from this channel"
+)]
+pub struct DeleteBoard {
+    #[option(description = "The project whose board should be removed")]
+    pub project: String,
+}
+
+pub async fn handle_board_delete(
+    args: DeleteBoard,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(channel) = interaction.channel.as_ref() else {
+        return Ok(ephemeral_message(format!(
+            "{} Boards can only be removed from a channel.",
+            Emojis::RED_X
+        )));
+    };
+    let projects = state.todoist_cache.projects().await?;
+    let Some(project) = find_project(&projects, &args.project) else {
+        return Ok(ephemeral_message(format!(
+            "{} Could not find a project named `{}`.",
+            Emojis::RED_X,
+            args.project
+        )));
+    };
+
+    let channel_id = channel.id.get();
+    let removed = state
+        .boards
+        .update(|boards| {
+            let message_ids = boards
+                .iter()
+                .filter(|(_, board)| {
+                    board.channel_id == channel_id && board.project_id == project.id
+                })
+                .map(|(message_id, _)| *message_id)
+                .collect::<Vec<_>>();
+            for message_id in &message_ids {
+                boards.remove(message_id);
+            }
+            message_ids
+        })
+        .await?;
+    if removed.is_empty() {
+        return Ok(ephemeral_message(format!(
+            "{} There is no board for **{}** in this channel.",
+            Emojis::RED_X,
+            project.name
+        )));
+    }
+
+    for message_id in &removed {
+        if let Err(e) = state
+            .client
+            .delete_message(channel.id, Id::new(*message_id))
+            .await
+        {
+            warn!("Failed to delete board {}: {}", message_id, e);
+        }
+    }
+    debug!(
+        "Removed {} boards for project {}",
+        removed.len(),
+        project.id
+    );
+    Ok(ephemeral_message(format!(
+        "{} Removed the board for **{}**.",
+        Emojis::GREEN_TICK,
+        project.name
+    )))
 }
 
 #[derive(Command)]
@@ -229,44 +1532,183 @@ pub async fn handle_today(
 
     let tasks = todoist::get_tasks_due_today(&state.todoist_client, timezone).await?;
 
-    let accent_color = if tasks.is_empty() {
-        0x00AA00 // Green for no tasks
-    } else {
-        0xAAAA00 // Yellow for tasks due
-    };
+    Ok(ephemeral_card(today_card(&tasks, &[])))
+}
 
-    let container = ContainerBuilder::new()
-        .accent_color(Some(accent_color))
-        .component(if tasks.is_empty() {
-            TextDisplayBuilder::new("You have no more tasks due today!".to_string()).build()
+#[derive(Command)]
+#[command(name = "projects", description = "List your Todoist projects")]
+pub struct ListProjects;
+
+pub async fn handle_projects(
+    _args: ListProjects,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+
+    let mut content = format!("You have **{}** projects:\n", tree.len());
+    for (index, entry) in tree.iter().enumerate() {
+        let favorite = if entry.project.is_favorite {
+            " ⭐"
         } else {
-            let mut content = format!("There are **{}** tasks due today:\n", tasks.len());
-            for task in tasks {
-                let mut task_format = format!("[{}]({})", task.content, task.get_url());
-
-                if let Some(due) = &task.due
-                    && !due.is_date_only()
-                    && let Ok(due_date) =
-                        <Due as TryInto<DateTime<FixedOffset>>>::try_into(due.clone())
-                {
-                    let due_unix_time = due_date.timestamp();
-                    task_format.push_str(&format!(" <t:{}:t>", due_unix_time));
-                }
+            ""
+        };
+        let line = format!(
+            "{}- [{}]({}){}\n",
+            "  ".repeat(entry.depth),
+            entry.path,
+            entry.project.get_url(),
+            favorite
+        );
+        // Leave room for the note about the remaining projects
+        if content.len() + line.len() > MAX_TEXT_DISPLAY_LENGTH - 32 {
+            content.push_str(&format!("*…and {} more*", tree.len() - index));
+            break;
+        }
+        content.push_str(&line);
+    }
 
-                content.push_str(&format!("- {}\n", task_format));
-            }
-            TextDisplayBuilder::new(content).build()
-        })
+    let container = ContainerBuilder::new()
+        .component(TextDisplayBuilder::new(content).build())
         .build();
 
-    Ok(InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(InteractionResponseData {
-            components: Some(vec![container.into()]),
-            flags: Some(MessageFlags::IS_COMPONENTS_V2 | MessageFlags::EPHEMERAL),
-            ..Default::default()
-        }),
-    })
+    Ok(ephemeral_card(container))
+}
+
+#[derive(Command)]
+#[command(
+    name = "project view",
+    description = "Show the sections and tasks in a project"
+)]
+pub struct ViewProject {
+    #[option(description = "The project to show")]
+    pub project: String,
+}
+
+pub async fn handle_project_view(
+    args: ViewProject,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let projects = state.todoist_cache.projects().await?;
+    let Some(project) = find_project(&projects, &args.project) else {
+        return Ok(ephemeral_message(format!(
+            "{} Could not find a project named `{}`.",
+            Emojis::RED_X,
+            args.project
+        )));
+    };
+
+    let container = browse::project_view(&state, &project.id, 0).await?;
+    Ok(ephemeral_card(container))
+}
+
+/// Builds the input sent to the LLM for the provided message, including the message it replies to
+/// and the previous `CONTEXT_MESSAGE_COUNT` messages in the channel as labelled context.
+///
+/// Image attachments are sent as images when the provider supports them, and all other attachments
+/// are listed by file name and URL.
+async fn build_llm_input(state: &AppState, message: &Message) -> PromptInput {
+    let previous_messages = get_previous_messages(state, message).await;
+    let referenced_message = get_referenced_message(state, message).await;
+
+    let mut text = String::new();
+    if previous_messages.is_empty() && referenced_message.is_none() {
+        text.push_str(&message_to_string(message));
+    } else {
+        if !previous_messages.is_empty() {
+            text.push_str("Previous messages in the channel (oldest first):\n");
+            for previous in previous_messages.iter().rev() {
+                text.push_str(&format_context_message(previous));
+                text.push('\n');
+            }
+            text.push('\n');
+        }
+        if let Some(referenced_message) = referenced_message {
+            text.push_str("Message being replied to:\n");
+            text.push_str(&format_context_message(&referenced_message));
+            text.push_str("\n\n");
+        }
+        text.push_str("Message to create the reminder from:\n");
+        text.push_str(&format_context_message(message));
+    }
+
+    let supports_images = state.llm_provider.supports_images();
+    let mut images = Vec::new();
+    let mut attachments = Vec::new();
+    for attachment in &message.attachments {
+        let content_type = attachment.content_type.as_deref().unwrap_or_default();
+        if supports_images && images.len() < MAX_IMAGES && ImageInput::is_supported(content_type) {
+            images.push(ImageInput {
+                url: attachment.url.clone(),
+            });
+        } else {
+            attachments.push(format!("- {}: {}", attachment.filename, attachment.url));
+        }
+    }
+    if !attachments.is_empty() {
+        text.push_str("\n\nAttachments:\n");
+        text.push_str(&attachments.join("\n"));
+    }
+
+    PromptInput { text, images }
+}
+
+/// Gets the message the provided message is replying to, if any.
+async fn get_referenced_message(state: &AppState, message: &Message) -> Option<Message> {
+    if let Some(referenced) = &message.referenced_message {
+        return Some(*referenced.clone());
+    }
+    let reference = message.reference.as_ref()?;
+    let channel_id = reference.channel_id.unwrap_or(message.channel_id);
+    let message_id = reference.message_id?;
+
+    match state.client.message(channel_id, message_id).await {
+        Ok(response) => response.model().await.ok(),
+        Err(e) => {
+            warn!("Failed to fetch referenced message {}: {}", message_id, e);
+            None
+        }
+    }
+}
+
+/// Gets the messages sent before the provided message, newest first.
+async fn get_previous_messages(state: &AppState, message: &Message) -> Vec<Message> {
+    let count = env::var("CONTEXT_MESSAGE_COUNT")
+        .ok()
+        .and_then(|count| count.parse::<u16>().ok())
+        .unwrap_or(0)
+        .min(MAX_CONTEXT_MESSAGES);
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let response = state
+        .client
+        .channel_messages(message.channel_id)
+        .before(message.id)
+        .limit(count)
+        .await;
+    match response {
+        Ok(response) => response.models().await.unwrap_or_default(),
+        Err(e) => {
+            warn!(
+                "Failed to fetch previous messages in channel {}: {}",
+                message.channel_id, e
+            );
+            Vec::new()
+        }
+    }
+}
+
+fn format_context_message(message: &Message) -> String {
+    let author = message
+        .author
+        .global_name
+        .as_deref()
+        .unwrap_or(&message.author.name);
+    format!("[{}]: {}", author, message_to_string(message).trim_end())
 }
 
 fn message_to_string(message: &Message) -> String {
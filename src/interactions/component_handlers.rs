@@ -0,0 +1,685 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use twilight_model::application::interaction::Interaction;
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::Container;
+use twilight_model::http::interaction::InteractionResponse;
+use twilight_model::id::Id;
+use twilight_util::builder::message::{ContainerBuilder, SectionBuilder, TextDisplayBuilder};
+
+use crate::AppState;
+use crate::announce;
+use crate::card_refs;
+use crate::emoji::Emojis;
+use crate::interactions::browse;
+use crate::interactions::cards::{
+    comment_added_card, comment_picker_card, ephemeral_message, task_card, update_card,
+    view_task_button,
+};
+use crate::interactions::command_handlers;
+use crate::interactions::components::{CustomId, selected_values};
+use crate::interactions::picker::{self, NO_SECTION};
+use crate::reminders;
+use crate::todoist::tree::{PATH_SEPARATOR, ProjectTree};
+use crate::todoist::{self, MoveTask, UpdateTaskBody, http::models::Task};
+
+/// The select menu used to pick the project to move a task to.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectSelect {
+    pub task_id: String,
+}
+
+impl CustomId for ProjectSelect {
+    const PREFIX: &'static str = "project_select";
+}
+
+/// The buttons used to page through the projects in the [`ProjectSelect`] menu.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectPage {
+    #[serde(rename = "t")]
+    pub task_id: String,
+    #[serde(rename = "n")]
+    pub page: usize,
+}
+
+impl CustomId for ProjectPage {
+    const PREFIX: &'static str = "project_page";
+}
+
+/// The multi-select menu used to set a task's labels.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LabelSelect {
+    pub task_id: String,
+}
+
+impl CustomId for LabelSelect {
+    const PREFIX: &'static str = "label_select";
+}
+
+/// The select menu used to pick the section of a project to move a task to.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SectionSelect {
+    #[serde(rename = "t")]
+    pub task_id: String,
+    #[serde(rename = "p")]
+    pub project_id: String,
+}
+
+impl CustomId for SectionSelect {
+    const PREFIX: &'static str = "section_select";
+    const VERSION: u8 = 2;
+}
+
+/// The buttons used to page through the sections in the [`SectionSelect`] menu.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SectionPage {
+    #[serde(rename = "t")]
+    pub task_id: String,
+    #[serde(rename = "p")]
+    pub project_id: String,
+    #[serde(rename = "n")]
+    pub page: usize,
+}
+
+impl CustomId for SectionPage {
+    const PREFIX: &'static str = "section_page";
+}
+
+/// The select menu used to pick the project of each task created by "Add Multiple To-Dos".
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskProjectSelect {
+    pub task_id: String,
+}
+
+impl CustomId for TaskProjectSelect {
+    const PREFIX: &'static str = "task_project_select";
+}
+
+/// The button used to complete a task.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompleteTaskButton {
+    pub task_id: String,
+}
+
+impl CustomId for CompleteTaskButton {
+    const PREFIX: &'static str = "complete_task";
+}
+
+/// The buttons on a due-time reminder used to send it again later.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SnoozeReminderButton {
+    #[serde(rename = "t")]
+    pub task_id: String,
+    #[serde(rename = "m")]
+    pub minutes: u32,
+}
+
+impl CustomId for SnoozeReminderButton {
+    const PREFIX: &'static str = "snooze";
+}
+
+/// The button used to reopen a completed task.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReopenTaskButton {
+    pub task_id: String,
+}
+
+impl CustomId for ReopenTaskButton {
+    const PREFIX: &'static str = "reopen_task";
+}
+
+/// The select menu used to pick the task a message is added to as a comment.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentTaskSelect {
+    #[serde(rename = "c")]
+    pub channel_id: u64,
+    #[serde(rename = "m")]
+    pub message_id: u64,
+}
+
+impl CustomId for CommentTaskSelect {
+    const PREFIX: &'static str = "comment_task";
+}
+
+/// The button used to publicly mark the message a task was created from as tracked.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnounceButton {
+    #[serde(rename = "c")]
+    pub channel_id: u64,
+    #[serde(rename = "m")]
+    pub message_id: u64,
+}
+
+impl CustomId for AnnounceButton {
+    const PREFIX: &'static str = "announce";
+}
+
+/// The buttons used to page through the tasks in the [`CommentTaskSelect`] menu.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentTaskPage {
+    #[serde(rename = "c")]
+    pub channel_id: u64,
+    #[serde(rename = "m")]
+    pub message_id: u64,
+    #[serde(rename = "n")]
+    pub page: usize,
+}
+
+impl CustomId for CommentTaskPage {
+    const PREFIX: &'static str = "comment_page";
+}
+
+/// The buttons used to page through the sections of a project in `/project view`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectViewPage {
+    #[serde(rename = "p")]
+    pub project_id: String,
+    #[serde(rename = "n")]
+    pub page: usize,
+}
+
+impl CustomId for ProjectViewPage {
+    const PREFIX: &'static str = "project_view";
+}
+
+/// The buttons used to show, and page through, the open tasks in a section of a project.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SectionTasks {
+    #[serde(rename = "p")]
+    pub project_id: String,
+    #[serde(rename = "s")]
+    pub section_id: String,
+    #[serde(rename = "n")]
+    pub page: usize,
+}
+
+impl CustomId for SectionTasks {
+    const PREFIX: &'static str = "section_tasks";
+}
+
+pub async fn handle_project_select(
+    select: ProjectSelect,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(project_id) = selected_values(&interaction).first() else {
+        warn!("No project ID found in selection");
+        return Ok(ephemeral_message(format!(
+            "{} No project ID found in selection.",
+            Emojis::RED_X
+        )));
+    };
+
+    let task = todoist::move_task(
+        &state.todoist_client,
+        MoveTask {
+            task_id: select.task_id,
+            project_id: Some(project_id.clone()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    state.todoist_cache.invalidate_tasks().await;
+    state.recent_projects.record(project_id);
+    info!("Moved task {} to project {}", task.id, project_id);
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), interaction.message.clone());
+
+    let note = format!(
+        "{} Moved to **{}**",
+        Emojis::GREEN_TICK,
+        project_name(&state, project_id).await
+    );
+    let picker = picker::section_picker(&state, &task.id, project_id, 0).await?;
+    Ok(update_card(
+        render_card(&state, &task, vec![note], picker).await?,
+    ))
+}
+
+pub async fn handle_project_page(
+    page: ProjectPage,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let task = todoist::get_task(&state.todoist_client, &page.task_id).await?;
+    let picker = picker::project_picker(&state, &task.id, page.page).await?;
+    Ok(update_card(
+        render_card(&state, &task, Vec::new(), picker).await?,
+    ))
+}
+
+pub async fn handle_section_select(
+    select: SectionSelect,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(value) = selected_values(&interaction).first() else {
+        warn!("No section ID found in selection");
+        return Ok(ephemeral_message(format!(
+            "{} No section ID found in selection.",
+            Emojis::RED_X
+        )));
+    };
+    let section_id = (value != NO_SECTION).then(|| value.clone());
+
+    // Moving to the project removes the task from its section
+    let task = todoist::move_task(
+        &state.todoist_client,
+        MoveTask {
+            task_id: select.task_id,
+            project_id: section_id.is_none().then(|| select.project_id.clone()),
+            section_id: section_id.clone(),
+            parent_id: None,
+        },
+    )
+    .await?;
+    state.todoist_cache.invalidate_tasks().await;
+    state.recent_projects.record(&select.project_id);
+    info!(
+        "Moved task {} to project {} and section {:?}",
+        task.id, select.project_id, section_id
+    );
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), interaction.message.clone());
+
+    let note = format!(
+        "{} Moved to **{}**",
+        Emojis::GREEN_TICK,
+        task_location(&state, &task).await
+    );
+    let picker = picker::section_picker(&state, &task.id, &select.project_id, 0).await?;
+    Ok(update_card(
+        render_card(&state, &task, vec![note], picker).await?,
+    ))
+}
+
+pub async fn handle_section_page(
+    page: SectionPage,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let task = todoist::get_task(&state.todoist_client, &page.task_id).await?;
+    let picker = picker::section_picker(&state, &task.id, &page.project_id, page.page).await?;
+    Ok(update_card(
+        render_card(&state, &task, Vec::new(), picker).await?,
+    ))
+}
+
+/// Moves one of the tasks created by "Add Multiple To-Dos" to the selected project, keeping the
+/// choice shown in its menu.
+pub async fn handle_task_project_select(
+    select: TaskProjectSelect,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(project_id) = selected_values(&interaction).first() else {
+        warn!("No project ID found in selection");
+        return Ok(ephemeral_message(format!(
+            "{} No project ID found in selection.",
+            Emojis::RED_X
+        )));
+    };
+    let custom_id = select.to_custom_id()?;
+
+    let task = todoist::move_task(
+        &state.todoist_client,
+        MoveTask {
+            task_id: select.task_id,
+            project_id: Some(project_id.clone()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    state.todoist_cache.invalidate_tasks().await;
+    state.recent_projects.record(project_id);
+    info!("Moved task {} to project {}", task.id, project_id);
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), None);
+
+    let Some(Component::Container(container)) = interaction
+        .message
+        .as_ref()
+        .and_then(|message| message.components.first())
+    else {
+        return Ok(ephemeral_message(format!(
+            "{} Moved **{}** to **{}**",
+            Emojis::GREEN_TICK,
+            task.content,
+            project_name(&state, project_id).await
+        )));
+    };
+    let mut container = container.clone();
+    show_selected(&mut container.components, &custom_id, project_id);
+    Ok(update_card(container))
+}
+
+/// Marks the option with the value as chosen in the select menu with the custom ID, so the menu
+/// keeps showing it once the message is updated.
+fn show_selected(components: &mut [Component], custom_id: &str, value: &str) {
+    for component in components {
+        match component {
+            Component::Container(container) => {
+                show_selected(&mut container.components, custom_id, value)
+            }
+            Component::ActionRow(row) => show_selected(&mut row.components, custom_id, value),
+            Component::SelectMenu(select) if select.custom_id == custom_id => {
+                for option in select.options.iter_mut().flatten() {
+                    option.default = option.value == value;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Renders the task's card as it is shown when it changes outside of the card, with the project
+/// picker.
+pub async fn live_card(state: &AppState, task: &Task) -> Result<Container> {
+    let picker = picker::project_picker(state, &task.id, 0).await?;
+    render_card(state, task, Vec::new(), picker).await
+}
+
+/// Renders the task's card, including its location, due date, open subtasks and the label picker.
+///
+/// `notes` are shown below the task's details, e.g. to confirm what was just changed.
+pub async fn render_card(
+    state: &AppState,
+    task: &Task,
+    mut notes: Vec<String>,
+    mut picker: Vec<Component>,
+) -> Result<Container> {
+    picker.extend(picker::label_picker(state, task).await?);
+    notes.insert(0, task_details(state, task).await);
+    let subtasks = state
+        .todoist_cache
+        .open_tasks()
+        .await?
+        .iter()
+        .filter(|subtask| subtask.parent_id.as_deref() == Some(task.id.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    task_card(task, &subtasks, &notes, picker)
+}
+
+/// Describes where the task is and when it is due, e.g. "📁 **Work / Client A / Design** · 📅 Due
+/// Friday, 24 October 2025 17:00".
+pub async fn task_details(state: &AppState, task: &Task) -> String {
+    let mut details = format!("📁 **{}**", task_location(state, task).await);
+    if let Some(due) = &task.due
+        && let Ok(due_date) = DateTime::<FixedOffset>::try_from(due.clone())
+    {
+        let style = if due.is_date_only() { 'D' } else { 'f' };
+        details.push_str(&format!(" · 📅 Due <t:{}:{}>", due_date.timestamp(), style));
+        if due.is_recurring {
+            details.push_str(&format!(" ({})", due.string));
+        }
+    }
+    details
+}
+
+/// Gets the path of the task's project and section, e.g. "Work / Client A / Design".
+async fn task_location(state: &AppState, task: &Task) -> String {
+    let mut location = project_name(state, &task.project_id).await;
+    if let Some(section_id) = &task.section_id
+        && let Ok(sections) = state.todoist_cache.sections().await
+        && let Some(section) = sections.iter().find(|section| &section.id == section_id)
+    {
+        location.push_str(&format!("{}{}", PATH_SEPARATOR, section.name));
+    }
+    location
+}
+
+/// Gets the project's path, e.g. "Work / Client A", falling back to its ID.
+async fn project_name(state: &AppState, project_id: &str) -> String {
+    state
+        .todoist_cache
+        .projects()
+        .await
+        .ok()
+        .and_then(|projects| {
+            ProjectTree::new(&projects)
+                .path(project_id)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| project_id.to_string())
+}
+
+pub async fn handle_label_select(
+    select: LabelSelect,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let labels = selected_values(&interaction).to_vec();
+    let task = todoist::update_task(
+        &state.todoist_client,
+        &select.task_id,
+        UpdateTaskBody {
+            labels: Some(labels.clone()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    state.todoist_cache.invalidate_tasks().await;
+    info!("Set labels of task {} to {:?}", task.id, labels);
+    card_refs::spawn_refresh(state.clone(), task.id.clone(), interaction.message.clone());
+
+    let note = if labels.is_empty() {
+        format!("{} Removed all labels", Emojis::GREEN_TICK)
+    } else {
+        format!(
+            "{} Labelled with {}",
+            Emojis::GREEN_TICK,
+            labels
+                .iter()
+                .map(|label| format!("**@{}**", label))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    let picker = picker::project_picker(&state, &task.id, 0).await?;
+    Ok(update_card(
+        render_card(&state, &task, vec![note], picker).await?,
+    ))
+}
+
+pub async fn handle_comment_task_select(
+    select: CommentTaskSelect,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(task_id) = selected_values(&interaction).first() else {
+        warn!("No task ID found in selection");
+        return Ok(ephemeral_message(format!(
+            "{} No task ID found in selection.",
+            Emojis::RED_X
+        )));
+    };
+
+    // The message is only fetched if it was forgotten, e.g. after a restart
+    let message = match state.pending_comments.get(select.message_id) {
+        Some(message) => message,
+        None => match state
+            .client
+            .message(Id::new(select.channel_id), Id::new(select.message_id))
+            .await
+        {
+            Ok(response) => response.model().await?,
+            Err(e) => {
+                warn!("Failed to fetch message {}: {}", select.message_id, e);
+                return Ok(ephemeral_message(format!(
+                    "{} The message could not be read. Use \"Add as comment to task…\" on it \
+                    again.",
+                    Emojis::RED_X
+                )));
+            }
+        },
+    };
+    let task = todoist::get_task(&state.todoist_client, task_id).await?;
+    command_handlers::add_message_comment(&state, &interaction, &message, &task).await?;
+    state.pending_comments.remove(select.message_id);
+    info!(
+        "Added message {} as a comment to task {}",
+        message.id, task.id
+    );
+
+    Ok(update_card(comment_added_card(&task)))
+}
+
+pub async fn handle_comment_task_page(
+    page: CommentTaskPage,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let picker =
+        picker::comment_task_picker(&state, page.channel_id, page.message_id, page.page).await?;
+    Ok(update_card(comment_picker_card(picker)))
+}
+
+pub async fn handle_project_view_page(
+    page: ProjectViewPage,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let container = browse::project_view(&state, &page.project_id, page.page).await?;
+    Ok(update_card(container))
+}
+
+pub async fn handle_section_tasks(
+    button: SectionTasks,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let container =
+        browse::section_view(&state, &button.project_id, &button.section_id, button.page).await?;
+    Ok(update_card(container))
+}
+
+pub async fn handle_announce(
+    button: AnnounceButton,
+    interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let Some(user_id) = interaction.author_id() else {
+        return Ok(ephemeral_message(format!(
+            "{} Could not determine who clicked the button.",
+            Emojis::RED_X
+        )));
+    };
+    let announced = announce::announce(
+        &state,
+        Id::new(button.channel_id),
+        Id::new(button.message_id),
+        user_id,
+    )
+    .await?;
+    Ok(ephemeral_message(if announced {
+        format!("{} Announced in the channel.", Emojis::GREEN_TICK)
+    } else {
+        format!("{} This message has already been announced.", Emojis::RED_X)
+    }))
+}
+
+pub async fn handle_complete_task(
+    button: CompleteTaskButton,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    todoist::close_task(&state.todoist_client, &button.task_id).await?;
+    state.todoist_cache.invalidate_tasks().await;
+    info!("Completed task {}", button.task_id);
+    card_refs::spawn_refresh(state.clone(), button.task_id.clone(), None);
+    Ok(ephemeral_message(format!(
+        "{} Completed task.",
+        Emojis::GREEN_TICK
+    )))
+}
+
+pub async fn handle_snooze_reminder(
+    button: SnoozeReminderButton,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    let task = todoist::get_task(&state.todoist_client, &button.task_id).await?;
+    let until = reminders::snooze(&state, &task, button.minutes).await?;
+    info!("Snoozed reminder for task {} until {}", task.id, until);
+
+    let container = ContainerBuilder::new()
+        .component(
+            SectionBuilder::new(view_task_button(&task))
+                .component(
+                    TextDisplayBuilder::new(format!(
+                        "💤 Snoozed **{}** until <t:{}:t>",
+                        task.content,
+                        until.timestamp()
+                    ))
+                    .build(),
+                )
+                .build(),
+        )
+        .build();
+    Ok(update_card(container))
+}
+
+pub async fn handle_reopen_task(
+    button: ReopenTaskButton,
+    _interaction: Arc<Interaction>,
+    state: Arc<AppState>,
+) -> Result<InteractionResponse> {
+    todoist::reopen_task(&state.todoist_client, &button.task_id).await?;
+    state.todoist_cache.invalidate_tasks().await;
+    info!("Reopened task {}", button.task_id);
+    card_refs::spawn_refresh(state.clone(), button.task_id.clone(), None);
+    Ok(ephemeral_message(format!(
+        "{} Reopened task.",
+        Emojis::GREEN_TICK
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::channel::message::component::SelectMenuType;
+    use twilight_util::builder::message::{
+        ActionRowBuilder, SelectMenuBuilder, SelectMenuOptionBuilder,
+    };
+
+    use super::*;
+
+    #[test]
+    fn show_selected_marks_only_the_chosen_option() {
+        let select = |custom_id: &str| -> Component {
+            ActionRowBuilder::new()
+                .component(
+                    SelectMenuBuilder::new(custom_id.to_string(), SelectMenuType::Text)
+                        .option(SelectMenuOptionBuilder::new("Work", "1").build())
+                        .option(SelectMenuOptionBuilder::new("Home", "2").build())
+                        .build(),
+                )
+                .build()
+                .into()
+        };
+        let mut container = ContainerBuilder::new()
+            .component(select("a"))
+            .component(select("b"))
+            .build();
+        show_selected(&mut container.components, "a", "2");
+
+        let defaults = container
+            .components
+            .iter()
+            .map(|row| match row {
+                Component::ActionRow(row) => match &row.components[0] {
+                    Component::SelectMenu(select) => select
+                        .options
+                        .iter()
+                        .flatten()
+                        .map(|option| option.default)
+                        .collect::<Vec<_>>(),
+                    _ => panic!("Expected a select menu"),
+                },
+                _ => panic!("Expected an action row"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(defaults, vec![vec![false, true], vec![false, false]]);
+    }
+}
@@ -0,0 +1,234 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use anyhow::Result;
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tracing::debug;
+use twilight_model::application::interaction::{Interaction, InteractionData};
+use twilight_model::http::interaction::InteractionResponse;
+
+/// The maximum length of a component's custom ID, as limited by Discord.
+const MAX_CUSTOM_ID_LENGTH: usize = 100;
+
+/// The maximum length of a select menu option's value, as limited by Discord.
+const MAX_VALUE_LENGTH: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Custom ID `{0}` is {1} characters long, which is over the limit of 100")]
+    TooLong(String, usize),
+    #[error("Malformed custom ID `{0}`")]
+    Malformed(String),
+    #[error("This message is out of date. Please run the command again.")]
+    Outdated,
+    #[error("Failed to decode component payload: {0}")]
+    Payload(#[from] serde_json::Error),
+}
+
+/// A typed payload stored in a component's custom ID.
+///
+/// Custom IDs are encoded as `<prefix>:<version>:<json payload>`, and are routed to the handler
+/// registered for the prefix.
+pub trait CustomId: Serialize + DeserializeOwned + Send + 'static {
+    /// The prefix identifying the component's handler.
+    const PREFIX: &'static str;
+    /// The version of the payload. Bump this when the payload changes so components on old
+    /// messages are rejected instead of being decoded incorrectly.
+    const VERSION: u8 = 1;
+
+    fn to_custom_id(&self) -> Result<String, Error> {
+        let custom_id = format!(
+            "{}:{}:{}",
+            Self::PREFIX,
+            Self::VERSION,
+            serde_json::to_string(self)?
+        );
+        if custom_id.len() > MAX_CUSTOM_ID_LENGTH {
+            return Err(Error::TooLong(custom_id.clone(), custom_id.len()));
+        }
+        Ok(custom_id)
+    }
+
+    fn from_custom_id(custom_id: &str) -> Result<Self, Error> {
+        let mut parts = custom_id.splitn(3, ':');
+        let (Some(prefix), Some(version), Some(payload)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(Error::Malformed(custom_id.to_string()));
+        };
+        if prefix != Self::PREFIX {
+            return Err(Error::Malformed(custom_id.to_string()));
+        }
+        if version.parse::<u8>().ok() != Some(Self::VERSION) {
+            return Err(Error::Outdated);
+        }
+        Ok(serde_json::from_str(payload)?)
+    }
+}
+
+/// Encodes a select menu option's value.
+pub fn encode_value<T: Serialize>(value: &T) -> Result<String, Error> {
+    let value = serde_json::to_string(value)?;
+    if value.len() > MAX_VALUE_LENGTH {
+        return Err(Error::TooLong(value.clone(), value.len()));
+    }
+    Ok(value)
+}
+
+/// Decodes a select menu option's value.
+pub fn decode_value<T: DeserializeOwned>(value: &str) -> Result<T, Error> {
+    Ok(serde_json::from_str(value)?)
+}
+
+/// Gets the values selected in a select menu interaction.
+pub fn selected_values(interaction: &Interaction) -> &[String] {
+    match interaction.data.as_ref() {
+        Some(InteractionData::MessageComponent(data)) => &data.values,
+        _ => &[],
+    }
+}
+
+pub type ComponentFuture = Pin<Box<dyn Future<Output = Result<InteractionResponse>> + Send>>;
+type ComponentHandler<S> =
+    Arc<dyn Fn(&str, Arc<Interaction>, Arc<S>) -> Result<ComponentFuture> + Send + Sync>;
+
+/// Message component handlers keyed by their custom ID prefix.
+pub struct ComponentHandlers<S> {
+    handlers: HashMap<&'static str, ComponentHandler<S>>,
+}
+
+impl<S> Default for ComponentHandlers<S> {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+}
+
+impl<S> ComponentHandlers<S>
+where
+    S: Send + Sync + 'static,
+{
+    /// Registers a handler for components whose custom ID was created from `T`.
+    pub fn register<T, F, Fut>(&mut self, handler: F)
+    where
+        T: CustomId,
+        F: Fn(T, Arc<Interaction>, Arc<S>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<InteractionResponse>> + Send + 'static,
+    {
+        self.handlers.insert(
+            T::PREFIX,
+            Arc::new(move |custom_id: &str, interaction, state| {
+                let payload = T::from_custom_id(custom_id)?;
+                Ok(Box::pin(handler(payload, interaction, state)))
+            }),
+        );
+    }
+
+    /// Executes the handler for the custom ID. Returns `None` if there is no handler registered for
+    /// the custom ID's prefix.
+    pub async fn execute(
+        &self,
+        custom_id: &str,
+        interaction: Arc<Interaction>,
+        state: Arc<S>,
+    ) -> Option<Result<InteractionResponse>> {
+        let prefix = custom_id.split(':').next().unwrap_or_default();
+        debug!("Executing component handler for prefix: {}", prefix);
+        let handler = self.handlers.get(prefix)?;
+        match handler(custom_id, interaction, state) {
+            Ok(future) => Some(future.await),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct TestButton {
+        task_id: String,
+    }
+
+    impl CustomId for TestButton {
+        const PREFIX: &'static str = "test";
+        const VERSION: u8 = 2;
+    }
+
+    #[test]
+    fn custom_id_round_trip() {
+        let button = TestButton {
+            task_id: "123".to_string(),
+        };
+        let custom_id = button.to_custom_id().unwrap();
+        assert_eq!(custom_id, r#"test:2:{"task_id":"123"}"#);
+        assert_eq!(TestButton::from_custom_id(&custom_id).unwrap(), button);
+    }
+
+    #[test]
+    fn custom_id_payload_can_contain_separator() {
+        let button = TestButton {
+            task_id: "a:b:c".to_string(),
+        };
+        let custom_id = button.to_custom_id().unwrap();
+        assert_eq!(TestButton::from_custom_id(&custom_id).unwrap(), button);
+    }
+
+    #[test]
+    fn custom_id_over_limit() {
+        let button = TestButton {
+            task_id: "x".repeat(MAX_CUSTOM_ID_LENGTH),
+        };
+        assert!(matches!(button.to_custom_id(), Err(Error::TooLong(_, _))));
+
+        // The longest payload that fits is accepted
+        let overhead = r#"test:2:{"task_id":""}"#.len();
+        let button = TestButton {
+            task_id: "x".repeat(MAX_CUSTOM_ID_LENGTH - overhead),
+        };
+        assert_eq!(button.to_custom_id().unwrap().len(), MAX_CUSTOM_ID_LENGTH);
+    }
+
+    #[test]
+    fn custom_id_outdated_version() {
+        assert!(matches!(
+            TestButton::from_custom_id(r#"test:1:{"task_id":"123"}"#),
+            Err(Error::Outdated)
+        ));
+    }
+
+    #[test]
+    fn custom_id_malformed() {
+        assert!(matches!(
+            TestButton::from_custom_id("test:2"),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            TestButton::from_custom_id(r#"other:2:{"task_id":"123"}"#),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            TestButton::from_custom_id("test:2:not json"),
+            Err(Error::Payload(_))
+        ));
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let value = ("project".to_string(), 3u32);
+        let encoded = encode_value(&value).unwrap();
+        assert_eq!(decode_value::<(String, u32)>(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn value_over_limit() {
+        assert!(matches!(
+            encode_value(&"x".repeat(MAX_VALUE_LENGTH)),
+            Err(Error::TooLong(_, _))
+        ));
+    }
+}
@@ -0,0 +1,451 @@
+use std::{fmt::Display, future::Future, sync::Arc, time::Duration};
+
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tokio::time;
+use tokio::{select, time::timeout};
+use tracing::{debug, error, warn};
+use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::{
+    application::interaction::{Interaction, InteractionData, InteractionType},
+    channel::message::MessageFlags,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+};
+use twilight_util::builder::message::{ContainerBuilder, TextDisplayBuilder};
+
+use crate::{
+    AppState,
+    emoji::Emojis,
+    interactions::{autocomplete::get_focused_option, resolve_command_path},
+};
+
+/// How long a handler can take before the interaction is deferred and the response is sent by
+/// editing the original response instead.
+const DEFER_AFTER: Duration = Duration::from_secs(1);
+
+/// How long a deferred handler can take before its response is dropped.
+const DEFERRED_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Error)]
+pub enum DispatchError {
+    #[error("Interaction is missing its data")]
+    MissingData,
+    #[error("Unsupported interaction type {0:?}")]
+    UnsupportedType(InteractionType),
+    #[error("No response was produced for the interaction")]
+    NoResponse,
+}
+
+/// The response to an interaction produced by the [`Dispatcher`].
+///
+/// If the handler was deferred, its response is only sent once [`Dispatched::acknowledge`] is
+/// called, as Discord rejects edits to a response it has not received yet. Dropping it without
+/// acknowledging drops the handler's response.
+pub struct Dispatched {
+    pub response: InteractionResponse,
+    acknowledged: Option<oneshot::Sender<()>>,
+}
+
+impl Dispatched {
+    /// Lets a deferred handler send its response. Call this once [`Dispatched::response`] has
+    /// been sent to Discord.
+    pub fn acknowledge(&mut self) {
+        if let Some(acknowledged) = self.acknowledged.take() {
+            let _ = acknowledged.send(());
+        }
+    }
+}
+
+impl From<InteractionResponse> for Dispatched {
+    fn from(response: InteractionResponse) -> Self {
+        Self {
+            response,
+            acknowledged: None,
+        }
+    }
+}
+
+/// Routes interactions to the registered command, component and autocomplete handlers.
+///
+/// The dispatcher does not depend on how interactions are received, so it is shared by the HTTP
+/// interactions endpoint and the gateway. Handlers that take longer than [`DEFER_AFTER`] are
+/// deferred, and their response is sent later by editing the original response.
+#[derive(Clone)]
+pub struct Dispatcher {
+    state: Arc<AppState>,
+}
+
+impl Dispatcher {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+
+    /// Runs the handler for the interaction, returning the response to send to Discord.
+    pub async fn dispatch(
+        &self,
+        interaction: Arc<Interaction>,
+    ) -> Result<Dispatched, DispatchError> {
+        match (&interaction.kind, &interaction.data) {
+            (InteractionType::Ping, _) => Ok(InteractionResponse {
+                kind: InteractionResponseType::Pong,
+                data: None,
+            }
+            .into()),
+            (
+                InteractionType::ApplicationCommand,
+                Some(InteractionData::ApplicationCommand(command)),
+            ) => Ok(self.command(&interaction, command).await),
+            (InteractionType::MessageComponent, Some(InteractionData::MessageComponent(data))) => {
+                self.component(&interaction, data).await
+            }
+            (
+                InteractionType::ApplicationCommandAutocomplete,
+                Some(InteractionData::ApplicationCommand(command)),
+            ) => Ok(self.autocomplete(command).await.into()),
+            (
+                InteractionType::ApplicationCommand
+                | InteractionType::MessageComponent
+                | InteractionType::ApplicationCommandAutocomplete,
+                _,
+            ) => Err(DispatchError::MissingData),
+            (kind, _) => Err(DispatchError::UnsupportedType(*kind)),
+        }
+    }
+
+    /// Runs the slash command, falling back to the context command with the command's name.
+    async fn command(&self, interaction: &Arc<Interaction>, command: &CommandData) -> Dispatched {
+        debug!("Processing application command: {}", command.name);
+
+        if let Some((command_path, options)) = resolve_command_path(command) {
+            debug!("Resolved command path: {}", command_path);
+            let state = Arc::clone(&self.state);
+            let handler_interaction = Arc::clone(interaction);
+            let response = self
+                .run(interaction, async move {
+                    let handler_state = Arc::clone(&state);
+                    state
+                        .slash_commands
+                        .execute(&command_path, handler_interaction, options, handler_state)
+                        .await
+                })
+                .await;
+            if let Some(response) = response {
+                return response;
+            }
+        }
+
+        let Some(handler) = self.state.context_commands.get(&command.name).cloned() else {
+            warn!("No handler found for command: {}", command.name);
+            return error_response(format!("No handler found for command: `{}`", command.name))
+                .into();
+        };
+        let state = Arc::clone(&self.state);
+        let handler_interaction = Arc::clone(interaction);
+        self.run(interaction, async move {
+            Some(
+                handler(handler_interaction, state)
+                    .await
+                    .unwrap_or_else(handler_error_response),
+            )
+        })
+        .await
+        .unwrap_or_else(|| {
+            error_response("An error occurred while processing your command.").into()
+        })
+    }
+
+    async fn component(
+        &self,
+        interaction: &Arc<Interaction>,
+        data: &MessageComponentInteractionData,
+    ) -> Result<Dispatched, DispatchError> {
+        debug!("Processing message component interaction: {:?}", data);
+        let custom_id = data.custom_id.clone();
+        let state = Arc::clone(&self.state);
+        let handler_interaction = Arc::clone(interaction);
+
+        self.run(interaction, async move {
+            let handler_state = Arc::clone(&state);
+            let result = state
+                .component_handlers
+                .execute(&custom_id, handler_interaction, handler_state)
+                .await;
+            Some(match result {
+                Some(result) => result.unwrap_or_else(handler_error_response),
+                None => {
+                    warn!("No handler for message component: {}", custom_id);
+                    error_response(format!("No handler for message component: `{}`", custom_id))
+                }
+            })
+        })
+        .await
+        .ok_or(DispatchError::NoResponse)
+    }
+
+    /// Gets the choices for the focused option. Autocomplete is never deferred, as Discord does
+    /// not allow it.
+    async fn autocomplete(&self, command: &CommandData) -> InteractionResponse {
+        let choices = match resolve_command_path(command) {
+            Some((command_path, options)) => match get_focused_option(&options) {
+                Some((option, value)) => {
+                    self.state
+                        .autocomplete
+                        .execute(&command_path, option, value, Arc::clone(&self.state))
+                        .await
+                }
+                None => Vec::new(),
+            },
+            None => Vec::new(),
+        };
+        InteractionResponse {
+            kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
+            data: Some(InteractionResponseData {
+                choices: Some(choices),
+                ..InteractionResponseData::default()
+            }),
+        }
+    }
+
+    /// Runs the handler in its own task, returning its response if it finishes within
+    /// [`DEFER_AFTER`]. Otherwise, defers the interaction and edits the original response once the
+    /// handler finishes and the deferral has been acknowledged.
+    async fn run<F>(&self, interaction: &Arc<Interaction>, handler: F) -> Option<Dispatched>
+    where
+        F: Future<Output = Option<InteractionResponse>> + Send + 'static,
+    {
+        let mut handle = tokio::spawn(handler);
+        select! {
+            result = &mut handle => {
+                result
+                    .unwrap_or_else(|e| {
+                        error!("Handler panicked: {}", e);
+                        Some(error_response(format!(
+                            "An error occurred while processing your command: {}",
+                            e
+                        )))
+                    })
+                    .map(Dispatched::from)
+            }
+            _ = time::sleep(DEFER_AFTER) => {
+                debug!("Handler timed out. Returning deferred response.");
+                let deferred = deferred_response(interaction);
+                let state = Arc::clone(&self.state);
+                let interaction = Arc::clone(interaction);
+                let (acknowledged, is_acknowledged) = oneshot::channel();
+                tokio::spawn(async move {
+                    let response = timeout(DEFERRED_TIMEOUT, handle)
+                        .await
+                        .ok()
+                        .and_then(|result| result.ok())
+                        .flatten();
+                    if is_acknowledged.await.is_err() {
+                        warn!(
+                            "Interaction {} was not acknowledged, dropping its response",
+                            interaction.id
+                        );
+                        return;
+                    }
+                    if let Some(response) = response
+                        && let Err(e) = send_deferred_response(&state, &interaction, response).await
+                    {
+                        error!("Failed to send delayed response: {}", e);
+                    }
+                });
+                Some(Dispatched {
+                    response: deferred,
+                    acknowledged: Some(acknowledged),
+                })
+            }
+        }
+    }
+}
+
+/// Defers the interaction. Components are deferred as an update to their message, so cards are
+/// still edited in place, while commands are deferred as a new ephemeral message.
+fn deferred_response(interaction: &Interaction) -> InteractionResponse {
+    if interaction.kind == InteractionType::MessageComponent {
+        return InteractionResponse {
+            kind: InteractionResponseType::DeferredUpdateMessage,
+            data: None,
+        };
+    }
+    InteractionResponse {
+        kind: InteractionResponseType::DeferredChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..InteractionResponseData::default()
+        }),
+    }
+}
+
+/// Replaces the deferred response with the handler's response.
+///
+/// For components, the original response is the message the component is attached to, so only
+/// updates to it are sent that way. Any other response, such as an error, is sent as a follow up
+/// message instead of replacing the card.
+async fn send_deferred_response(
+    state: &AppState,
+    interaction: &Interaction,
+    response: InteractionResponse,
+) -> anyhow::Result<()> {
+    let data = response.data.unwrap_or_default();
+    let client = state.client.interaction(state.app_id);
+    if interaction.kind == InteractionType::MessageComponent
+        && response.kind != InteractionResponseType::UpdateMessage
+    {
+        let mut followup = client
+            .create_followup(&interaction.token)
+            .attachments(data.attachments.as_deref().unwrap_or_default())
+            .embeds(data.embeds.as_deref().unwrap_or_default())
+            .components(data.components.as_deref().unwrap_or_default())
+            .flags(data.flags.unwrap_or(MessageFlags::empty()));
+        if let Some(content) = data.content.as_deref() {
+            followup = followup.content(content);
+        }
+        followup.await?;
+        return Ok(());
+    }
+
+    client
+        .update_response(&interaction.token)
+        .attachments(&data.attachments.unwrap_or_default())
+        .content(data.content.as_deref())
+        .embeds(data.embeds.as_deref())
+        .components(data.components.as_deref())
+        .flags(data.flags.unwrap_or(MessageFlags::empty()))
+        .await?;
+    Ok(())
+}
+
+/// Renders an error returned by a handler.
+fn handler_error_response(e: anyhow::Error) -> InteractionResponse {
+    error_response(format!("An error occurred: {}", e))
+}
+
+/// Renders an error as an ephemeral red card, so every failure looks the same to the user.
+pub fn error_response(message: impl Display) -> InteractionResponse {
+    let container = ContainerBuilder::new()
+        .accent_color(Some(0xFF0000))
+        .component(TextDisplayBuilder::new(format!("{} {}", Emojis::RED_X, message)).build())
+        .build();
+
+    InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            components: Some(vec![container.into()]),
+            flags: Some(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+    use async_trait::async_trait;
+    use twilight_commands::executor::{ContextCommands, SlashCommands};
+    use twilight_http::Client;
+    use twilight_model::id::Id;
+
+    use super::*;
+    use crate::interactions::autocomplete::AutocompleteHandlers;
+    use crate::interactions::components::ComponentHandlers;
+    use crate::interactions::picker::{PendingComments, RecentProjects};
+    use crate::llm::prompt::PromptInput;
+    use crate::llm::{LLMProvider, PromptResponse};
+    use crate::storage::JsonStore;
+    use crate::todoist::cache::TodoistCache;
+    use crate::todoist::http::TodoistHttpClient;
+
+    struct NoProvider;
+
+    #[async_trait]
+    impl LLMProvider for NoProvider {
+        async fn generate_reminder(
+            &self,
+            _user_input: &PromptInput,
+        ) -> anyhow::Result<PromptResponse> {
+            bail!("No LLM provider in tests")
+        }
+
+        async fn generate_reminders(
+            &self,
+            _user_input: &PromptInput,
+        ) -> anyhow::Result<Vec<PromptResponse>> {
+            bail!("No LLM provider in tests")
+        }
+    }
+
+    fn dispatcher() -> Dispatcher {
+        let _ = rustls::crypto::CryptoProvider::install_default(
+            rustls::crypto::ring::default_provider(),
+        );
+        let todoist_client = Arc::new(TodoistHttpClient::new("token"));
+        Dispatcher::new(Arc::new(AppState {
+            app_id: Id::new(1),
+            verifier: None,
+            client: Arc::new(Client::new("token".to_string())),
+            context_commands: Arc::new(ContextCommands::default()),
+            slash_commands: Arc::new(SlashCommands::default()),
+            autocomplete: Arc::new(AutocompleteHandlers::default()),
+            component_handlers: Arc::new(ComponentHandlers::default()),
+            todoist_cache: Arc::new(TodoistCache::new(Arc::clone(&todoist_client))),
+            todoist_client,
+            recent_projects: Arc::new(RecentProjects::default()),
+            pending_comments: Arc::new(PendingComments::default()),
+            digests: Arc::new(JsonStore::empty("digests.json")),
+            reminders: Arc::new(JsonStore::empty("reminders.json")),
+            review: Arc::new(JsonStore::empty("review.json")),
+            cards: Arc::new(JsonStore::empty("cards.json")),
+            boards: Arc::new(JsonStore::empty("boards.json")),
+            announcements: Arc::new(JsonStore::empty("announcements.json")),
+            todoist_webhook: None,
+            llm_provider: Arc::new(NoProvider),
+        }))
+    }
+
+    /// Builds an interaction of the given type without any data.
+    fn interaction(kind: InteractionType) -> Arc<Interaction> {
+        let mut interaction: Interaction = serde_json::from_str(
+            r#"{"id":"1","application_id":"1","type":1,"token":"token","authorizing_integration_owners":{}}"#,
+        )
+        .unwrap();
+        interaction.kind = kind;
+        Arc::new(interaction)
+    }
+
+    #[tokio::test]
+    async fn ping_is_answered_with_pong() {
+        let dispatched = dispatcher()
+            .dispatch(interaction(InteractionType::Ping))
+            .await
+            .unwrap();
+        assert_eq!(dispatched.response.kind, InteractionResponseType::Pong);
+        assert!(dispatched.response.data.is_none());
+    }
+
+    #[tokio::test]
+    async fn missing_data() {
+        let dispatcher = dispatcher();
+        for kind in [
+            InteractionType::ApplicationCommand,
+            InteractionType::MessageComponent,
+            InteractionType::ApplicationCommandAutocomplete,
+        ] {
+            let result = dispatcher.dispatch(interaction(kind)).await;
+            assert!(matches!(result, Err(DispatchError::MissingData)));
+        }
+    }
+
+    #[tokio::test]
+    async fn unsupported_type() {
+        let result = dispatcher()
+            .dispatch(interaction(InteractionType::ModalSubmit))
+            .await;
+        assert!(matches!(
+            result,
+            Err(DispatchError::UnsupportedType(InteractionType::ModalSubmit))
+        ));
+    }
+}
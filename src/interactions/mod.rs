@@ -4,9 +4,20 @@ use twilight_model::application::interaction::application_command::{
     CommandData, CommandDataOption, CommandOptionValue,
 };
 
+pub mod autocomplete;
+pub mod browse;
+pub mod cards;
 pub mod command_handlers;
+pub mod component_handlers;
+pub mod components;
+pub mod dispatch;
+pub mod picker;
 pub mod verifier;
 
+/// The maximum length of the text in a message, as limited by Discord. The limit applies to all
+/// text displays in the message combined.
+pub const MAX_TEXT_DISPLAY_LENGTH: usize = 4000;
+
 pub fn resolve_command_path(interaction: &CommandData) -> Option<(String, Vec<CommandDataOption>)> {
     debug!("Resolving command path for interaction: {:?}", interaction);
     let mut path = vec![interaction.name.clone()];
@@ -45,3 +56,16 @@ fn is_option_sub(options: &[CommandDataOption]) -> bool {
         CommandOptionValue::SubCommand(_) | CommandOptionValue::SubCommandGroup(_)
     )
 }
+
+/// Truncates the text to at most `max_chars` characters, ending it with an ellipsis if shortened.
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated = text
+        .chars()
+        .take(max_chars.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    truncated
+}
@@ -0,0 +1,435 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use twilight_model::channel::Message;
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::{
+    Button, ButtonStyle, SelectMenuOption, SelectMenuType,
+};
+use twilight_util::builder::message::{
+    ActionRowBuilder, ButtonBuilder, SelectMenuBuilder, SelectMenuOptionBuilder,
+};
+
+use crate::AppState;
+use crate::interactions::component_handlers::{
+    CommentTaskPage, CommentTaskSelect, LabelSelect, ProjectPage, ProjectSelect, SectionPage,
+    SectionSelect, TaskProjectSelect,
+};
+use crate::interactions::components::CustomId;
+use crate::interactions::truncate;
+use crate::todoist::http::models::Task;
+use crate::todoist::tree::{ProjectEntry, ProjectTree};
+
+/// The maximum number of options in a select menu, as limited by Discord.
+const PAGE_SIZE: usize = 25;
+
+/// The maximum length of a select menu option's label.
+const MAX_LABEL_LENGTH: usize = 100;
+
+/// The number of recently used projects remembered for ranking.
+const MAX_RECENT_PROJECTS: usize = 10;
+
+/// How long a message picked with "Add as comment to task…" is remembered for.
+const PENDING_COMMENT_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// The value of the section select option that removes the task from its section.
+pub const NO_SECTION: &str = "none";
+
+/// The projects most recently picked in the section picker, most recent first.
+#[derive(Default)]
+pub struct RecentProjects {
+    projects: Mutex<VecDeque<String>>,
+}
+
+impl RecentProjects {
+    pub fn record(&self, project_id: &str) {
+        let mut projects = self.projects.lock().unwrap();
+        projects.retain(|id| id != project_id);
+        projects.push_front(project_id.to_string());
+        projects.truncate(MAX_RECENT_PROJECTS);
+    }
+
+    fn position(&self, project_id: &str) -> Option<usize> {
+        self.projects
+            .lock()
+            .unwrap()
+            .iter()
+            .position(|id| id == project_id)
+    }
+}
+
+/// The messages picked with "Add as comment to task…", keyed by message ID, as resolved when the
+/// command was used, along with the user who picked them.
+///
+/// The bot may not be able to read the channel the message is in, so the message is kept until a
+/// task is picked instead of being fetched again.
+#[derive(Default)]
+pub struct PendingComments {
+    messages: Mutex<HashMap<u64, (Instant, Option<u64>, Message)>>,
+}
+
+impl PendingComments {
+    pub fn insert(&self, user_id: Option<u64>, message: Message) {
+        let mut messages = self.messages.lock().unwrap();
+        messages.retain(|_, (added, _, _)| added.elapsed() < PENDING_COMMENT_LIFETIME);
+        messages.insert(message.id.get(), (Instant::now(), user_id, message));
+    }
+
+    pub fn get(&self, message_id: u64) -> Option<Message> {
+        self.messages
+            .lock()
+            .unwrap()
+            .get(&message_id)
+            .filter(|(added, _, _)| added.elapsed() < PENDING_COMMENT_LIFETIME)
+            .map(|(_, _, message)| message.clone())
+    }
+
+    /// Gets the message the user most recently picked, for when they search for the task with
+    /// `/comment` instead of picking it from the menu.
+    pub fn latest(&self, user_id: u64) -> Option<Message> {
+        self.messages
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(added, picked_by, _)| {
+                added.elapsed() < PENDING_COMMENT_LIFETIME && *picked_by == Some(user_id)
+            })
+            .max_by_key(|(added, _, _)| *added)
+            .map(|(_, _, message)| message.clone())
+    }
+
+    pub fn remove(&self, message_id: u64) {
+        self.messages.lock().unwrap().remove(&message_id);
+    }
+}
+
+/// Orders the projects with favourites first, then recently used projects, then the remaining
+/// projects in their position in the project tree.
+pub fn rank_projects<'a, 'b>(
+    tree: &'b ProjectTree<'a>,
+    recent: &RecentProjects,
+) -> Vec<&'b ProjectEntry<'a>> {
+    let mut entries = tree.iter().collect::<Vec<_>>();
+    // The sort is stable, so projects with the same rank keep their tree order
+    entries.sort_by_key(|entry| {
+        (
+            !entry.project.is_favorite,
+            recent.position(&entry.project.id).unwrap_or(usize::MAX),
+        )
+    });
+    entries
+}
+
+/// Builds the first step of the picker, selecting the project to move the task to.
+pub async fn project_picker(
+    state: &AppState,
+    task_id: &str,
+    page: usize,
+) -> Result<Vec<Component>> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    let projects = rank_projects(&tree, &state.recent_projects);
+    let pages = projects.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut select = SelectMenuBuilder::new(
+        ProjectSelect {
+            task_id: task_id.to_string(),
+        }
+        .to_custom_id()?,
+        SelectMenuType::Text,
+    )
+    .placeholder(page_placeholder("Move to project", page, pages));
+
+    for entry in projects.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        select = select.option(project_option(entry));
+    }
+
+    let mut rows = vec![
+        ActionRowBuilder::new()
+            .component(select.build())
+            .build()
+            .into(),
+    ];
+    if pages > 1 {
+        let previous = ProjectPage {
+            task_id: task_id.to_string(),
+            page: page.saturating_sub(1),
+        };
+        let next = ProjectPage {
+            task_id: task_id.to_string(),
+            page: page + 1,
+        };
+        rows.push(page_buttons(
+            previous.to_custom_id()?,
+            next.to_custom_id()?,
+            page,
+            pages,
+            None,
+        ));
+    }
+    Ok(rows)
+}
+
+/// Builds the select menu used to pick the project of one of several tasks created at once.
+///
+/// A message can only hold a few of these, so there is no second step for the section and only
+/// the first page of projects is listed.
+pub async fn task_project_select(state: &AppState, task_id: &str) -> Result<Component> {
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+    let mut select = SelectMenuBuilder::new(
+        TaskProjectSelect {
+            task_id: task_id.to_string(),
+        }
+        .to_custom_id()?,
+        SelectMenuType::Text,
+    )
+    .placeholder("Move to project");
+    for entry in rank_projects(&tree, &state.recent_projects)
+        .iter()
+        .take(PAGE_SIZE)
+    {
+        select = select.option(project_option(entry));
+    }
+    Ok(ActionRowBuilder::new()
+        .component(select.build())
+        .build()
+        .into())
+}
+
+/// Builds the option picking the project, marking favourites with a star.
+fn project_option(entry: &ProjectEntry) -> SelectMenuOption {
+    let label = if entry.project.is_favorite {
+        format!("⭐ {}", entry.path)
+    } else {
+        entry.path.clone()
+    };
+    SelectMenuOptionBuilder::new(truncate(&label, MAX_LABEL_LENGTH), entry.project.id.clone())
+        .build()
+}
+
+/// Builds the second step of the picker, selecting the section of the project to move the task to.
+pub async fn section_picker(
+    state: &AppState,
+    task_id: &str,
+    project_id: &str,
+    page: usize,
+) -> Result<Vec<Component>> {
+    let sections = state.todoist_cache.sections().await?;
+    let mut sections = sections
+        .iter()
+        .filter(|section| {
+            section.project_id == project_id && !section.is_archived && !section.is_deleted
+        })
+        .collect::<Vec<_>>();
+    sections.sort_by_key(|section| section.section_order);
+
+    // The first option removes the task from its section
+    let options = std::iter::once(("No section".to_string(), NO_SECTION.to_string()))
+        .chain(
+            sections
+                .iter()
+                .map(|section| (section.name.clone(), section.id.clone())),
+        )
+        .collect::<Vec<_>>();
+    let pages = options.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut select = SelectMenuBuilder::new(
+        SectionSelect {
+            task_id: task_id.to_string(),
+            project_id: project_id.to_string(),
+        }
+        .to_custom_id()?,
+        SelectMenuType::Text,
+    )
+    .placeholder(page_placeholder("Move to section", page, pages));
+    for (label, value) in options.into_iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        select = select.option(
+            SelectMenuOptionBuilder::new(truncate(&label, MAX_LABEL_LENGTH), value).build(),
+        );
+    }
+
+    let back = ButtonBuilder::new(ButtonStyle::Secondary)
+        .label("Change project")
+        .custom_id(
+            ProjectPage {
+                task_id: task_id.to_string(),
+                page: 0,
+            }
+            .to_custom_id()?,
+        )
+        .build();
+
+    let mut rows = vec![
+        ActionRowBuilder::new()
+            .component(select.build())
+            .build()
+            .into(),
+    ];
+    if pages > 1 {
+        let previous = SectionPage {
+            task_id: task_id.to_string(),
+            project_id: project_id.to_string(),
+            page: page.saturating_sub(1),
+        };
+        let next = SectionPage {
+            task_id: task_id.to_string(),
+            project_id: project_id.to_string(),
+            page: page + 1,
+        };
+        rows.push(page_buttons(
+            previous.to_custom_id()?,
+            next.to_custom_id()?,
+            page,
+            pages,
+            Some(back),
+        ));
+    } else {
+        rows.push(ActionRowBuilder::new().component(back).build().into());
+    }
+    Ok(rows)
+}
+
+/// Builds the multi-select menu used to set the task's labels, with its current labels selected.
+///
+/// Returns `None` if there are no labels to pick from.
+pub async fn label_picker(state: &AppState, task: &Task) -> Result<Option<Component>> {
+    let labels = state.todoist_cache.labels().await?;
+    let mut names = labels
+        .iter()
+        .map(|label| (label.name.as_str(), label.is_favorite, label.order))
+        .collect::<Vec<_>>();
+    // The task's current labels come first so they are always shown and stay selected
+    names.sort_by_key(|(name, is_favorite, order)| {
+        (
+            !task.labels.iter().any(|label| label == name),
+            !is_favorite,
+            *order,
+        )
+    });
+    names.truncate(PAGE_SIZE);
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    let mut select = SelectMenuBuilder::new(
+        LabelSelect {
+            task_id: task.id.clone(),
+        }
+        .to_custom_id()?,
+        SelectMenuType::Text,
+    )
+    .placeholder("Labels");
+    for (name, _, _) in &names {
+        let mut option =
+            SelectMenuOptionBuilder::new(truncate(name, MAX_LABEL_LENGTH), name.to_string())
+                .build();
+        option.default = task.labels.iter().any(|label| label == name);
+        select = select.option(option);
+    }
+    let mut select = select.build();
+    select.min_values = Some(0);
+    select.max_values = Some(names.len() as u8);
+
+    Ok(Some(
+        ActionRowBuilder::new().component(select).build().into(),
+    ))
+}
+
+/// Builds the picker used to choose the task a message is added to as a comment, listing the most
+/// recently added open tasks first.
+pub async fn comment_task_picker(
+    state: &AppState,
+    channel_id: u64,
+    message_id: u64,
+    page: usize,
+) -> Result<Vec<Component>> {
+    let tasks = state.todoist_cache.open_tasks().await?;
+    let mut tasks = tasks.iter().collect::<Vec<_>>();
+    tasks.sort_by(|a, b| b.added_at.cmp(&a.added_at));
+    let pages = tasks.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut select = SelectMenuBuilder::new(
+        CommentTaskSelect {
+            channel_id,
+            message_id,
+        }
+        .to_custom_id()?,
+        SelectMenuType::Text,
+    )
+    .placeholder(page_placeholder("Pick a task", page, pages));
+    for task in tasks.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        select = select.option(
+            SelectMenuOptionBuilder::new(
+                truncate(&task.content, MAX_LABEL_LENGTH),
+                task.id.clone(),
+            )
+            .build(),
+        );
+    }
+
+    let mut rows = vec![
+        ActionRowBuilder::new()
+            .component(select.build())
+            .build()
+            .into(),
+    ];
+    if pages > 1 {
+        let previous = CommentTaskPage {
+            channel_id,
+            message_id,
+            page: page.saturating_sub(1),
+        };
+        let next = CommentTaskPage {
+            channel_id,
+            message_id,
+            page: page + 1,
+        };
+        rows.push(page_buttons(
+            previous.to_custom_id()?,
+            next.to_custom_id()?,
+            page,
+            pages,
+            None,
+        ));
+    }
+    Ok(rows)
+}
+
+fn page_placeholder(placeholder: &str, page: usize, pages: usize) -> String {
+    if pages > 1 {
+        format!("{} (page {} of {})", placeholder, page + 1, pages)
+    } else {
+        placeholder.to_string()
+    }
+}
+
+fn page_buttons(
+    previous_id: String,
+    next_id: String,
+    page: usize,
+    pages: usize,
+    extra: Option<Button>,
+) -> Component {
+    let previous = ButtonBuilder::new(ButtonStyle::Secondary)
+        .label("◀ Previous")
+        .custom_id(previous_id)
+        .disabled(page == 0)
+        .build();
+    let next = ButtonBuilder::new(ButtonStyle::Secondary)
+        .label("Next ▶")
+        .custom_id(next_id)
+        .disabled(page + 1 >= pages)
+        .build();
+
+    let mut row = ActionRowBuilder::new().component(previous).component(next);
+    if let Some(extra) = extra {
+        row = row.component(extra);
+    }
+    row.build().into()
+}
@@ -7,23 +7,58 @@ use twilight_model::id::Id;
 use twilight_model::id::marker::ApplicationMarker;
 use twilight_model::user::CurrentUser;
 
+use crate::announce::AnnounceDefaults;
+use crate::board::Boards;
+use crate::card_refs::CardRefs;
+use crate::digest::Digests;
+use crate::interactions::autocomplete::AutocompleteHandlers;
+use crate::interactions::components::ComponentHandlers;
+use crate::interactions::picker::{PendingComments, RecentProjects};
 use crate::interactions::verifier::Verifier;
 use crate::llm::Provider;
+use crate::reminders::ReminderState;
+use crate::review::ReviewState;
+use crate::storage::JsonStore;
+use crate::todoist::cache::TodoistCache;
 use crate::todoist::http::TodoistHttpClient;
+use crate::todoist::webhook::WebhookVerifier;
 
+pub mod announce;
+pub mod board;
+pub mod card_refs;
+pub mod digest;
 pub mod emoji;
+pub mod gateway;
 pub mod interactions;
 pub mod llm;
+pub mod reminders;
+pub mod review;
 pub mod routes;
+pub mod scheduler;
+pub mod storage;
 pub mod todoist;
 #[derive(Clone)]
 pub struct AppState {
     pub app_id: Id<ApplicationMarker>,
-    pub verifier: Arc<Verifier>,
+    /// Verifies requests to the interactions endpoint, unless interactions are received over the
+    /// gateway.
+    pub verifier: Option<Arc<Verifier>>,
     pub client: Arc<Client>,
     pub context_commands: Arc<ContextCommands<AppState>>,
     pub slash_commands: Arc<SlashCommands<AppState>>,
+    pub autocomplete: Arc<AutocompleteHandlers<AppState>>,
+    pub component_handlers: Arc<ComponentHandlers<AppState>>,
     pub todoist_client: Arc<TodoistHttpClient>,
+    pub todoist_cache: Arc<TodoistCache>,
+    pub recent_projects: Arc<RecentProjects>,
+    pub pending_comments: Arc<PendingComments>,
+    pub digests: Arc<JsonStore<Digests>>,
+    pub reminders: Arc<JsonStore<ReminderState>>,
+    pub review: Arc<JsonStore<ReviewState>>,
+    pub cards: Arc<JsonStore<CardRefs>>,
+    pub boards: Arc<JsonStore<Boards>>,
+    pub announcements: Arc<JsonStore<AnnounceDefaults>>,
+    pub todoist_webhook: Option<Arc<WebhookVerifier>>,
     pub llm_provider: Arc<Provider>,
 }
 
@@ -39,3 +74,15 @@ pub fn get_timezone_override() -> Option<chrono_tz::Tz> {
         .ok()
         .flatten()
 }
+
+/// Gets the timezone used when none is configured: the override if set, otherwise the system
+/// timezone, falling back to UTC.
+pub fn default_timezone() -> chrono_tz::Tz {
+    get_timezone_override()
+        .or_else(|| {
+            iana_time_zone::get_timezone()
+                .ok()
+                .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok())
+        })
+        .unwrap_or(chrono_tz::UTC)
+}
@@ -5,23 +5,32 @@ use tracing::debug;
 
 use crate::llm::{
     LLMProvider, PromptResponse,
-    claude::models::{InputMessage, MessageRequest, MessageResponse},
-    prompt::substitute_system_prompt,
+    claude::models::{
+        ContentBlock, ImageSource, InputMessage, MessageContent, MessageRequest, MessageResponse,
+    },
+    prompt::{ImageInput, PromptInput, substitute_system_prompt},
 };
 
 pub struct ClaudeHttpClient {
     client: Client,
     pub model: String,
     system_prompt: String,
+    multiple_system_prompt: String,
 }
 
 pub mod models;
 
 const CLAUDE_API_BASE_URL: &str = "https://api.anthropic.com/v1";
 const DEFAULT_SYSTEM_PROMPT: &str = include_str!("system_prompt.txt");
+const DEFAULT_MULTIPLE_SYSTEM_PROMPT: &str = include_str!("system_prompt_multiple.txt");
 
 impl ClaudeHttpClient {
-    pub fn new(api_token: &str, model: &str, system_prompt: Option<String>) -> Self {
+    pub fn new(
+        api_token: &str,
+        model: &str,
+        system_prompt: Option<String>,
+        multiple_system_prompt: Option<String>,
+    ) -> Self {
         let client = Client::builder()
             .user_agent("todoist-bot/0.1")
             .default_headers({
@@ -39,6 +48,8 @@ impl ClaudeHttpClient {
             client,
             model: model.to_string(),
             system_prompt: system_prompt.unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string()),
+            multiple_system_prompt: multiple_system_prompt
+                .unwrap_or_else(|| DEFAULT_MULTIPLE_SYSTEM_PROMPT.to_string()),
         }
     }
 
@@ -76,33 +87,90 @@ pub async fn message_create(
     Ok(message_response)
 }
 
-#[async_trait::async_trait]
-impl LLMProvider for ClaudeHttpClient {
-    async fn generate_reminder(&self, user_input: &str) -> Result<PromptResponse> {
-        debug!(
-            "Generating reminder from user input with Claude: {}",
-            user_input
-        );
+impl ClaudeHttpClient {
+    /// Sends a single user message with the provided system prompt and returns the text response.
+    async fn complete(
+        &self,
+        system_prompt: &str,
+        text: String,
+        images: &[ImageInput],
+    ) -> Result<String> {
+        let content = if images.is_empty() {
+            MessageContent::Text(text)
+        } else {
+            let mut blocks = images
+                .iter()
+                .map(|image| ContentBlock::Image {
+                    source: ImageSource::Url {
+                        url: image.url.clone(),
+                    },
+                })
+                .collect::<Vec<_>>();
+            blocks.push(ContentBlock::Text { text });
+            MessageContent::Blocks(blocks)
+        };
+
         let response = message_create(
             self,
             MessageRequest {
                 model: self.model.clone(),
                 messages: vec![InputMessage {
                     role: "user".to_string(),
-                    content: format!(
-                        "Create a reminder to add to my to-do list from the following message: {}",
-                        user_input
-                    ),
+                    content,
                 }],
                 max_tokens: 1000,
-                system: Some(substitute_system_prompt(&self.system_prompt)),
+                system: Some(substitute_system_prompt(system_prompt)),
             },
         )
         .await?;
 
         let response_str: String = response.into();
         debug!("Claude generated response: {}", response_str);
+        Ok(response_str)
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for ClaudeHttpClient {
+    async fn generate_reminder(&self, user_input: &PromptInput) -> Result<PromptResponse> {
+        debug!(
+            "Generating reminder from user input with Claude: {:?}",
+            user_input
+        );
+        let response_str = self
+            .complete(
+                &self.system_prompt,
+                format!(
+                    "Create a reminder to add to my to-do list from the following message: {}",
+                    user_input.text
+                ),
+                &user_input.images,
+            )
+            .await?;
+
+        serde_json::from_str(&response_str).context("Failed to decode response from claude")
+    }
+
+    async fn generate_reminders(&self, user_input: &PromptInput) -> Result<Vec<PromptResponse>> {
+        debug!(
+            "Generating reminders from user input with Claude: {:?}",
+            user_input
+        );
+        let response_str = self
+            .complete(
+                &self.multiple_system_prompt,
+                format!(
+                    "Create reminders to add to my to-do list from the following message: {}",
+                    user_input.text
+                ),
+                &user_input.images,
+            )
+            .await?;
 
         serde_json::from_str(&response_str).context("Failed to decode response from claude")
     }
+
+    fn supports_images(&self) -> bool {
+        true
+    }
 }
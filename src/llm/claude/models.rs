@@ -30,7 +30,30 @@ pub struct MessageResponse {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct InputMessage {
     pub role: String,
-    pub content: String,
+    pub content: MessageContent,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum ContentBlock {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "image")]
+    Image { source: ImageSource },
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum ImageSource {
+    #[serde(rename = "url")]
+    Url { url: String },
 }
 
 #[derive(Deserialize, Serialize, Debug)]
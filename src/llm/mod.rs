@@ -7,17 +7,28 @@ use async_trait::async_trait;
 use serde::Deserialize;
 use time::OffsetDateTime;
 
+use crate::llm::prompt::PromptInput;
+
 #[derive(Debug, Deserialize)]
 pub struct PromptResponse {
     pub title: String,
     #[serde(with = "time::serde::rfc3339::option")]
     pub due: Option<OffsetDateTime>,
     pub links: Option<Vec<String>>,
+    pub subtasks: Option<Vec<String>>,
 }
 
 pub type Provider = dyn LLMProvider + Send + Sync;
 
 #[async_trait]
 pub trait LLMProvider {
-    async fn generate_reminder(&self, user_input: &str) -> Result<PromptResponse>;
+    async fn generate_reminder(&self, user_input: &PromptInput) -> Result<PromptResponse>;
+
+    /// Generates a separate reminder for each action item in the user input.
+    async fn generate_reminders(&self, user_input: &PromptInput) -> Result<Vec<PromptResponse>>;
+
+    /// Returns true if images in the prompt input are sent to the model.
+    fn supports_images(&self) -> bool {
+        false
+    }
 }
@@ -2,6 +2,13 @@ use std::sync::Arc;
 
 use crate::llm::LLMProvider;
 use crate::llm::PromptResponse;
+use crate::llm::openai::models::ChatMessage;
+use crate::llm::openai::models::ChatRequest;
+use crate::llm::openai::models::ChatResponse;
+use crate::llm::openai::models::ContentPart;
+use crate::llm::openai::models::ImageUrl;
+use crate::llm::prompt::ImageInput;
+use crate::llm::prompt::PromptInput;
 use crate::llm::prompt::substitute_system_prompt;
 use anyhow::Context;
 use anyhow::Result;
@@ -17,10 +24,16 @@ use tokio::task::spawn_blocking;
 use tracing::debug;
 use tracing::info;
 
+pub mod models;
+
 pub struct OpenAIProvider {
     client: Arc<OpenAI>,
+    http_client: reqwest::Client,
+    api_endpoint: String,
     model: String,
     system_prompt: String,
+    multiple_system_prompt: String,
+    vision: bool,
 }
 
 impl OpenAIProvider {
@@ -38,6 +51,22 @@ impl OpenAIProvider {
             model, api_endpoint
         );
         let auth = Auth::new(api_key.as_str());
+        let vision = std::env::var("OPENAI_VISION").unwrap_or("false".to_string()) == "true";
+        if vision {
+            info!("Sending image attachments to OpenAI");
+        }
+
+        let http_client = reqwest::Client::builder()
+            .user_agent("todoist-bot/0.1")
+            .default_headers({
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key))?,
+                );
+                headers
+            })
+            .build()?;
 
         let system_prompt = {
             let path = std::env::var("OPENAI_SYSTEM_PROMPT_PATH");
@@ -48,56 +77,178 @@ impl OpenAIProvider {
             }
         };
 
+        let multiple_system_prompt = {
+            let path = std::env::var("OPENAI_MULTIPLE_SYSTEM_PROMPT_PATH");
+            match path {
+                Ok(p) => std::fs::read_to_string(p)
+                    .map_err(|e| anyhow::anyhow!("Failed to read system prompt file: {}", e))?,
+                Err(_) => include_str!("../claude/system_prompt_multiple.txt").to_string(),
+            }
+        };
+
         let client = OpenAI::new(auth, &api_endpoint);
         Ok(Self {
             client: Arc::new(client),
+            http_client,
+            api_endpoint,
             model,
             system_prompt,
+            multiple_system_prompt,
+            vision,
         })
     }
 }
 
-#[async_trait]
-impl LLMProvider for OpenAIProvider {
-    async fn generate_reminder(&self, user_input: &str) -> Result<PromptResponse> {
-        debug!("Generating reminder with OpenAI for input: {}", user_input);
-        let user_input = user_input.to_string();
+impl OpenAIProvider {
+    /// Sends a single user message with the provided system prompt and returns the text response.
+    async fn complete(
+        &self,
+        system_prompt: &str,
+        content: String,
+        images: &[ImageInput],
+    ) -> Result<String> {
+        if !images.is_empty() {
+            return self
+                .complete_with_images(system_prompt, content, images)
+                .await;
+        }
         let client = self.client.clone();
-        let system_prompt = substitute_system_prompt(&self.system_prompt);
+        let system_prompt = substitute_system_prompt(system_prompt);
         let model = self.model.clone();
         spawn_blocking(move || {
             let body = ChatBody {
-            model: model.clone(),
-            max_tokens: Some(1000),
-            frequency_penalty: None,
-            logit_bias: None,
+                model,
+                max_tokens: Some(1000),
+                frequency_penalty: None,
+                logit_bias: None,
+                messages: vec![
+                    Message {
+                        role: Role::System,
+                        content: system_prompt,
+                    },
+                    Message {
+                        role: Role::User,
+                        content,
+                    },
+                ],
+                n: Some(1),
+                presence_penalty: None,
+                stop: None,
+                user: None,
+                stream: Some(false),
+                temperature: None,
+                top_p: None,
+            };
+            let rs = client
+                .chat_completion_create(&body)
+                .map_err(|e| anyhow!(e))?;
+            let message = rs
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.message)
+                .ok_or(anyhow!("OpenAI response did not contain a message"))?;
+            debug!("OpenAI response message: {}", message.content);
+            Ok(message.content)
+        })
+        .await?
+    }
+
+    /// Sends a user message with image parts. The `openai_api_rust` client only supports text
+    /// content, so the chat completions endpoint is called directly.
+    async fn complete_with_images(
+        &self,
+        system_prompt: &str,
+        content: String,
+        images: &[ImageInput],
+    ) -> Result<String> {
+        let mut parts = vec![ContentPart::Text { text: content }];
+        parts.extend(images.iter().map(|image| ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: image.url.clone(),
+            },
+        }));
+
+        let request = ChatRequest {
+            model: self.model.clone(),
+            max_tokens: 1000,
             messages: vec![
-                Message {
-                    role: Role::System,
-                    content: system_prompt.clone(),
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: vec![ContentPart::Text {
+                        text: substitute_system_prompt(system_prompt),
+                    }],
                 },
-                Message {
-                    role: Role::User,
-                    content: format!(
-                        "Create a reminder to add to my to-do list from the following message: {}",
-                        user_input
-                    ),
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: parts,
                 },
             ],
-            n: Some(1),
-            presence_penalty: None,
-            stop: None,
-            user: None,
-            stream: Some(false),
-            temperature: None,
-            top_p: None,
         };
-        let rs = client.chat_completion_create(&body).map_err(|e| anyhow!(e))?;
-        let choice = rs.choices;
-        let message = &choice[0].message.as_ref().unwrap();
-        debug!("OpenAI response message: {}", message.content);
+        debug!("Sending OpenAI chat request: {:#?}", request);
+
+        let response: ChatResponse = self
+            .http_client
+            .post(format!("{}chat/completions", self.api_endpoint))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or(anyhow!("OpenAI response did not contain a message"))?;
+        debug!("OpenAI response message: {}", content);
+        Ok(content)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OpenAIProvider {
+    async fn generate_reminder(&self, user_input: &PromptInput) -> Result<PromptResponse> {
+        debug!(
+            "Generating reminder with OpenAI for input: {:?}",
+            user_input
+        );
+        let content = self
+            .complete(
+                &self.system_prompt,
+                format!(
+                    "Create a reminder to add to my to-do list from the following message: {}",
+                    user_input.text
+                ),
+                &user_input.images,
+            )
+            .await?;
+
+        serde_json::from_str(content.as_str()).context("Failed to parse OpenAI response")
+    }
+
+    async fn generate_reminders(&self, user_input: &PromptInput) -> Result<Vec<PromptResponse>> {
+        debug!(
+            "Generating reminders with OpenAI for input: {:?}",
+            user_input
+        );
+        let content = self
+            .complete(
+                &self.multiple_system_prompt,
+                format!(
+                    "Create reminders to add to my to-do list from the following message: {}",
+                    user_input.text
+                ),
+                &user_input.images,
+            )
+            .await?;
+
+        serde_json::from_str(content.as_str()).context("Failed to parse OpenAI response")
+    }
 
-        serde_json::from_str(message.content.as_str()).context("Failed to parse OpenAI response")
-        }).await.unwrap()
+    fn supports_images(&self) -> bool {
+        self.vision
     }
 }
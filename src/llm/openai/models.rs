@@ -0,0 +1,49 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Debug)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: Vec<ContentPart>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum ContentPart {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "image_url")]
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImageUrl {
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChatResponse {
+    pub id: String,
+    pub model: String,
+    pub choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChatChoice {
+    pub index: u32,
+    pub message: ChatResponseMessage,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChatResponseMessage {
+    pub role: String,
+    pub content: Option<String>,
+}
@@ -11,3 +11,37 @@ pub fn substitute_system_prompt(raw_prompt: &str) -> String {
     debug!("Using system prompt: \n{}", prompt);
     prompt
 }
+
+/// Image types accepted by vision-capable providers.
+const SUPPORTED_IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// The input used to generate reminders.
+#[derive(Debug, Default)]
+pub struct PromptInput {
+    pub text: String,
+    pub images: Vec<ImageInput>,
+}
+
+/// An image attached to the prompt, referenced by URL. Providers fetch the image themselves and
+/// detect its type, so only images of a supported type should be attached.
+#[derive(Debug, Clone)]
+pub struct ImageInput {
+    pub url: String,
+}
+
+impl ImageInput {
+    /// Returns true if the content type is an image type that can be sent to vision-capable providers.
+    pub fn is_supported(content_type: &str) -> bool {
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        SUPPORTED_IMAGE_TYPES.contains(&media_type)
+    }
+}
+
+impl From<String> for PromptInput {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            images: Vec::new(),
+        }
+    }
+}
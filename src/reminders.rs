@@ -0,0 +1,262 @@
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use twilight_model::channel::message::component::{ButtonStyle, Container};
+use twilight_model::channel::message::{EmojiReactionType, MessageFlags};
+use twilight_model::id::Id;
+use twilight_util::builder::message::{
+    ActionRowBuilder, ButtonBuilder, ContainerBuilder, SectionBuilder, TextDisplayBuilder,
+};
+
+use crate::AppState;
+use crate::card_refs::{self, CardKind, CardRef};
+use crate::interactions::cards::view_task_button;
+use crate::interactions::component_handlers::{CompleteTaskButton, SnoozeReminderButton};
+use crate::interactions::components::CustomId;
+use crate::todoist::http::models::Task;
+
+/// The file in the data directory the reminder state is stored in.
+pub const REMINDERS_FILE: &str = "reminders.json";
+
+/// How often open tasks are checked for reminders that are due.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The default number of minutes before a task is due that the reminder is sent.
+const DEFAULT_LEAD_MINUTES: i64 = 15;
+
+/// How long after a task was due a reminder is still sent, so starting the bot does not ping for
+/// every task that is long overdue.
+const LATE_REMINDER_WINDOW: TimeDelta = TimeDelta::hours(1);
+
+/// The reminders that have been sent and snoozed, keyed by task ID.
+///
+/// Reminders are tracked per due instance using the task's due date, so a recurring task is
+/// reminded again once it moves to its next occurrence.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ReminderState {
+    pub sent: HashMap<String, String>,
+    pub snoozed: HashMap<String, Snooze>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snooze {
+    /// The due date of the instance that was snoozed.
+    pub due: String,
+    pub until: DateTime<Utc>,
+}
+
+/// Gets the Discord user reminders are sent to, from `REMINDER_USER_ID`.
+///
+/// Reminders are disabled if it is not set.
+pub fn reminder_user_id() -> Option<u64> {
+    env::var("REMINDER_USER_ID")
+        .ok()
+        .and_then(|id| id.parse::<u64>().ok())
+}
+
+/// Gets how long before a task is due the reminder is sent, from `REMINDER_LEAD_MINUTES`.
+///
+/// Falls back to the default if it is not set or is out of range.
+fn lead_time() -> TimeDelta {
+    env::var("REMINDER_LEAD_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse::<i64>().ok())
+        .and_then(TimeDelta::try_minutes)
+        .unwrap_or(TimeDelta::minutes(DEFAULT_LEAD_MINUTES))
+}
+
+/// Gets the time the task is due, if it is due at a specific time rather than on a day.
+fn due_time(task: &Task) -> Option<DateTime<FixedOffset>> {
+    let due = task.due.as_ref().filter(|due| !due.is_date_only())?;
+    due.clone().try_into().ok()
+}
+
+/// Sends a reminder for every task due within the lead time, and for every snoozed reminder whose
+/// snooze has ended.
+pub async fn run_due_reminders(state: Arc<AppState>) -> Result<()> {
+    let Some(user_id) = reminder_user_id() else {
+        return Ok(());
+    };
+    let now = Utc::now();
+    let lead_time = lead_time();
+    let tasks = state.todoist_cache.open_tasks().await?;
+
+    let due = {
+        let reminders = state.reminders.read().await;
+        tasks
+            .iter()
+            .filter_map(|task| {
+                let due_date = task.due.as_ref()?.date.as_str();
+                let due_time = due_time(task)?.with_timezone(&Utc);
+                let snooze = reminders
+                    .snoozed
+                    .get(&task.id)
+                    .filter(|snooze| snooze.due == due_date);
+                let is_due = match snooze {
+                    Some(snooze) => snooze.until <= now,
+                    None => {
+                        reminders.sent.get(&task.id).map(String::as_str) != Some(due_date)
+                            && due_time
+                                .checked_sub_signed(lead_time)
+                                .is_none_or(|start| now >= start)
+                            && now <= due_time + LATE_REMINDER_WINDOW
+                    }
+                };
+                is_due.then_some(task)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Failed reminders are not recorded, so they are retried on the next check until the task is
+    // past the late reminder window
+    for task in due {
+        debug!("Sending reminder for task {}", task.id);
+        if let Err(e) = send_reminder(&state, user_id, task).await {
+            warn!("Failed to send reminder for task {}: {}", task.id, e);
+            continue;
+        }
+        info!("Sent reminder for task {}", task.id);
+        let due_date = task
+            .due
+            .as_ref()
+            .map(|due| due.date.clone())
+            .unwrap_or_default();
+        state
+            .reminders
+            .update(|reminders| {
+                reminders.snoozed.remove(&task.id);
+                reminders.sent.insert(task.id.clone(), due_date);
+            })
+            .await?;
+    }
+
+    // Forget reminders for tasks that have been completed or deleted
+    let open = tasks
+        .iter()
+        .map(|task| task.id.as_str())
+        .collect::<Vec<_>>();
+    let has_closed = {
+        let reminders = state.reminders.read().await;
+        reminders
+            .sent
+            .keys()
+            .chain(reminders.snoozed.keys())
+            .any(|id| !open.contains(&id.as_str()))
+    };
+    if has_closed {
+        state
+            .reminders
+            .update(|reminders| {
+                reminders.sent.retain(|id, _| open.contains(&id.as_str()));
+                reminders
+                    .snoozed
+                    .retain(|id, _| open.contains(&id.as_str()));
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn send_reminder(state: &AppState, user_id: u64, task: &Task) -> Result<()> {
+    let container = reminder_card(task)?;
+    let channel = state
+        .client
+        .create_private_channel(Id::new(user_id))
+        .await?
+        .model()
+        .await?;
+    let message = state
+        .client
+        .create_message(channel.id)
+        .components(&[container.into()])
+        .flags(MessageFlags::IS_COMPONENTS_V2)
+        .await?
+        .model()
+        .await?;
+    card_refs::record(
+        state,
+        &task.id,
+        CardRef::Message {
+            channel_id: message.channel_id.get(),
+            message_id: message.id.get(),
+            kind: CardKind::Reminder,
+        },
+    )
+    .await
+}
+
+/// Builds the reminder sent for a task, with buttons to complete or snooze it.
+pub fn reminder_card(task: &Task) -> Result<Container> {
+    let due = due_time(task)
+        .map(|due| format!("is due <t:{0}:R> (<t:{0}:t>)", due.timestamp()))
+        .unwrap_or_else(|| "is due soon".to_string());
+
+    let snooze_button = |minutes: u32, label: &str| -> Result<_> {
+        Ok(ButtonBuilder::new(ButtonStyle::Secondary)
+            .label(label)
+            .custom_id(
+                SnoozeReminderButton {
+                    task_id: task.id.clone(),
+                    minutes,
+                }
+                .to_custom_id()?,
+            )
+            .emoji(EmojiReactionType::Unicode {
+                name: "💤".to_string(),
+            })
+            .build())
+    };
+
+    Ok(ContainerBuilder::new()
+        .accent_color(Some(0xAAAA00))
+        .component(
+            SectionBuilder::new(view_task_button(task))
+                .component(
+                    TextDisplayBuilder::new(format!("⏰ **{}** {}", task.content, due)).build(),
+                )
+                .build(),
+        )
+        .component(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Success)
+                        .label("Complete")
+                        .custom_id(
+                            CompleteTaskButton {
+                                task_id: task.id.clone(),
+                            }
+                            .to_custom_id()?,
+                        )
+                        .emoji(EmojiReactionType::Unicode {
+                            name: "✅".to_string(),
+                        })
+                        .build(),
+                )
+                .component(snooze_button(15, "Snooze 15m")?)
+                .component(snooze_button(60, "Snooze 1h")?)
+                .build(),
+        )
+        .build())
+}
+
+/// Snoozes the reminder for the task's current due instance, sending it again after `minutes`.
+pub async fn snooze(state: &AppState, task: &Task, minutes: u32) -> Result<DateTime<Utc>> {
+    let until = Utc::now() + TimeDelta::minutes(minutes.into());
+    let due = task
+        .due
+        .as_ref()
+        .map(|due| due.date.clone())
+        .unwrap_or_default();
+    state
+        .reminders
+        .update(|reminders| {
+            reminders
+                .snoozed
+                .insert(task.id.clone(), Snooze { due, until });
+        })
+        .await?;
+    Ok(until)
+}
@@ -0,0 +1,389 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::channel::message::component::{Container, SeparatorSpacingSize};
+use twilight_model::id::Id;
+use twilight_util::builder::message::{ContainerBuilder, SeparatorBuilder, TextDisplayBuilder};
+
+use crate::AppState;
+use crate::default_timezone;
+use crate::digest::parse_schedule;
+use crate::interactions::MAX_TEXT_DISPLAY_LENGTH;
+use crate::interactions::command_handlers::CREATED_FROM_MESSAGE_PREFIX;
+use crate::todoist;
+use crate::todoist::http::models::Task;
+use crate::todoist::tree::ProjectTree;
+
+/// The file in the data directory the time of the last scheduled review is stored in.
+pub const REVIEW_FILE: &str = "review.json";
+
+/// How often the review schedule is checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When the scheduled review is posted if `REVIEW_SCHEDULE` is not set: Fridays at 5pm.
+const DEFAULT_REVIEW_SCHEDULE: &str = "0 17 * * Fri";
+
+/// The length of the period covered by the review, both looking back and looking ahead.
+const REVIEW_PERIOD: TimeDelta = TimeDelta::days(7);
+
+/// The maximum number of projects given their own section in the review.
+const MAX_PROJECTS: usize = 10;
+
+/// The maximum number of upcoming tasks listed for each project.
+const MAX_UPCOMING_TASKS: usize = 5;
+
+/// The length kept free for the "more projects" footer of the review, and for the "…and more"
+/// line of each project.
+const RESERVED_LENGTH: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ReviewState {
+    pub last_posted: Option<DateTime<Utc>>,
+    /// When each task the bot created from Discord was created, keyed by task ID. Tasks are
+    /// forgotten once they are older than the review period.
+    #[serde(default)]
+    pub created: HashMap<String, DateTime<Utc>>,
+}
+
+/// Remembers that the bot created the task from Discord, so it is counted in the review even if
+/// its description does not link to a message, e.g. tasks created with `/add`.
+pub async fn record_created(state: &AppState, task_id: &str) {
+    let now = Utc::now();
+    let result = state
+        .review
+        .update(|review| {
+            review
+                .created
+                .retain(|_, created_at| *created_at >= now - REVIEW_PERIOD);
+            review.created.insert(task_id.to_string(), now);
+        })
+        .await;
+    if let Err(e) = result {
+        warn!(
+            "Failed to record task {} as created from Discord: {}",
+            task_id, e
+        );
+    }
+}
+
+#[derive(Default)]
+struct ProjectReview<'a> {
+    completed: usize,
+    overdue: usize,
+    upcoming: Vec<(DateTime<FixedOffset>, &'a Task)>,
+}
+
+/// Builds the review of the past week: the tasks completed and created from Discord, the tasks
+/// that slipped, and the tasks due in the next week, broken down by project.
+pub async fn build_review(state: &AppState, timezone: Tz) -> Result<Container> {
+    let now = Utc::now();
+    let since = now - REVIEW_PERIOD;
+
+    let completed = todoist::get_completed_tasks(&state.todoist_client, since, now).await?;
+    let overdue = todoist::get_overdue_tasks(&state.todoist_client, Some(timezone)).await?;
+    let open = state.todoist_cache.open_tasks().await?;
+    let projects = state.todoist_cache.projects().await?;
+    let tree = ProjectTree::new(&projects);
+
+    // Tasks created before the bot recorded them are found by the link to their message
+    let mut created_from_discord = open
+        .iter()
+        .chain(completed.iter())
+        .filter(|task| task.description.starts_with(CREATED_FROM_MESSAGE_PREFIX))
+        .filter(|task| {
+            DateTime::parse_from_rfc3339(&task.added_at).is_ok_and(|added_at| added_at >= since)
+        })
+        .map(|task| task.id.clone())
+        .collect::<HashSet<_>>();
+    created_from_discord.extend(
+        state
+            .review
+            .read()
+            .await
+            .created
+            .iter()
+            .filter(|(_, created_at)| **created_at >= since)
+            .map(|(task_id, _)| task_id.clone()),
+    );
+    let created_from_discord = created_from_discord.len();
+
+    let today = now.with_timezone(&timezone).date_naive();
+    let next_week = today + REVIEW_PERIOD;
+    let mut by_project: HashMap<&str, ProjectReview> = HashMap::new();
+    for task in &completed {
+        by_project
+            .entry(task.project_id.as_str())
+            .or_default()
+            .completed += 1;
+    }
+    for task in &overdue {
+        by_project
+            .entry(task.project_id.as_str())
+            .or_default()
+            .overdue += 1;
+    }
+    for task in open.iter() {
+        let Some(due) = task
+            .due
+            .clone()
+            .and_then(|due| DateTime::<FixedOffset>::try_from(due).ok())
+        else {
+            continue;
+        };
+        let due_date = due.with_timezone(&timezone).date_naive();
+        if due_date >= today && due_date < next_week {
+            by_project
+                .entry(task.project_id.as_str())
+                .or_default()
+                .upcoming
+                .push((due, task));
+        }
+    }
+    let upcoming = by_project
+        .values()
+        .map(|review| review.upcoming.len())
+        .sum::<usize>();
+
+    let header = format!(
+        "## Weekly review\n<t:{}:D> – <t:{}:D>\n\
+        ✅ **{}** tasks completed\n\
+        💬 **{}** tasks created from Discord\n\
+        ⚠️ **{}** tasks overdue\n\
+        📅 **{}** tasks due in the next week",
+        since.timestamp(),
+        now.timestamp(),
+        completed.len(),
+        created_from_discord,
+        overdue.len(),
+        upcoming
+    );
+    let remaining = MAX_TEXT_DISPLAY_LENGTH.saturating_sub(header.chars().count());
+    let mut container = ContainerBuilder::new()
+        .component(TextDisplayBuilder::new(header).build())
+        .component(
+            SeparatorBuilder::new()
+                .divider(true)
+                .spacing(SeparatorSpacingSize::Small)
+                .build(),
+        );
+
+    // Projects are listed in the order they appear in Todoist, followed by any that are no longer
+    // active, such as archived projects with tasks completed this week
+    let mut project_ids = tree
+        .iter()
+        .map(|entry| entry.project.id.as_str())
+        .filter(|id| by_project.contains_key(id))
+        .collect::<Vec<_>>();
+    project_ids.extend(
+        by_project
+            .keys()
+            .filter(|id| tree.get(id).is_none())
+            .copied(),
+    );
+
+    let sections = project_sections(&project_ids, &mut by_project, &tree, remaining);
+    let shown = sections.len();
+    for content in sections {
+        container = container.component(TextDisplayBuilder::new(content).build());
+    }
+    if project_ids.len() > shown {
+        container = container.component(
+            TextDisplayBuilder::new(format!(
+                "-# {} more projects are not shown",
+                project_ids.len() - shown
+            ))
+            .build(),
+        );
+    }
+    if project_ids.is_empty() {
+        container = container
+            .component(TextDisplayBuilder::new("Nothing happened this week.".to_string()).build());
+    }
+
+    Ok(container.build())
+}
+
+/// Renders the section of each project, listing its upcoming tasks.
+///
+/// Discord limits the length of the text in the whole message, so tasks and projects stop being
+/// added once the next one would take the text over `remaining` characters.
+fn project_sections(
+    project_ids: &[&str],
+    by_project: &mut HashMap<&str, ProjectReview>,
+    tree: &ProjectTree,
+    remaining: usize,
+) -> Vec<String> {
+    let mut remaining = remaining.saturating_sub(RESERVED_LENGTH);
+    let mut sections = Vec::new();
+    for project_id in project_ids.iter().take(MAX_PROJECTS) {
+        let Some(review) = by_project.get_mut(project_id) else {
+            continue;
+        };
+        let name = tree.path(project_id).unwrap_or("Other projects");
+        let mut content = format!(
+            "### {}\n✅ {} completed · ⚠️ {} overdue · 📅 {} due next week\n",
+            name,
+            review.completed,
+            review.overdue,
+            review.upcoming.len()
+        );
+        let mut length = content.chars().count() + RESERVED_LENGTH;
+        if length > remaining {
+            break;
+        }
+        review.upcoming.sort_by_key(|(due, _)| *due);
+        let mut listed = 0;
+        for (due, task) in review.upcoming.iter().take(MAX_UPCOMING_TASKS) {
+            let line = format!(
+                "- [{}]({}) <t:{}:d>\n",
+                task.content,
+                task.get_url(),
+                due.timestamp()
+            );
+            length += line.chars().count();
+            if length > remaining {
+                break;
+            }
+            content.push_str(&line);
+            listed += 1;
+        }
+        if review.upcoming.len() > listed {
+            content.push_str(&format!(
+                "-# …and {} more\n",
+                review.upcoming.len() - listed
+            ));
+        }
+        remaining = remaining.saturating_sub(content.chars().count());
+        sections.push(content);
+    }
+    sections
+}
+
+/// Gets the channel the scheduled review is posted in, from `REVIEW_CHANNEL_ID`.
+///
+/// The scheduled review is disabled if it is not set.
+fn review_channel_id() -> Option<u64> {
+    env::var("REVIEW_CHANNEL_ID")
+        .ok()
+        .and_then(|id| id.parse::<u64>().ok())
+}
+
+/// Posts the review in the review channel when `REVIEW_SCHEDULE` says it is due.
+pub async fn run_scheduled_review(state: Arc<AppState>) -> Result<()> {
+    let Some(channel_id) = review_channel_id() else {
+        return Ok(());
+    };
+    let now = Utc::now();
+    let last_posted = state.review.read().await.last_posted;
+    let Some(last_posted) = last_posted else {
+        // Start the schedule from now rather than posting as soon as the review is enabled
+        state
+            .review
+            .update(|review| review.last_posted = Some(now))
+            .await?;
+        return Ok(());
+    };
+
+    let expression =
+        env::var("REVIEW_SCHEDULE").unwrap_or_else(|_| DEFAULT_REVIEW_SCHEDULE.to_string());
+    let schedule = parse_schedule(&expression)?;
+    let timezone = default_timezone();
+    let is_due = schedule
+        .after(&last_posted.with_timezone(&timezone))
+        .next()
+        .is_some_and(|next| next <= now);
+    if !is_due {
+        return Ok(());
+    }
+
+    match post_review(&state, channel_id, timezone).await {
+        Ok(()) => info!("Posted weekly review in channel {}", channel_id),
+        Err(e) => warn!("Failed to post weekly review: {}", e),
+    }
+    state
+        .review
+        .update(|review| review.last_posted = Some(now))
+        .await?;
+    Ok(())
+}
+
+async fn post_review(state: &AppState, channel_id: u64, timezone: Tz) -> Result<()> {
+    let container = build_review(state, timezone).await?;
+    state
+        .client
+        .create_message(Id::new(channel_id))
+        .components(&[container.into()])
+        .flags(MessageFlags::IS_COMPONENTS_V2)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_sections_fit_in_a_message() {
+        let due = DateTime::parse_from_rfc3339("2025-01-01T09:00:00Z").unwrap();
+        let tasks = (0..MAX_PROJECTS * MAX_UPCOMING_TASKS)
+            .map(|i| Task::for_test(&i.to_string(), &"A very long task name ".repeat(10)))
+            .collect::<Vec<_>>();
+        let project_ids = (0..MAX_PROJECTS).map(|i| i.to_string()).collect::<Vec<_>>();
+        let project_ids = project_ids.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut by_project: HashMap<&str, ProjectReview> = HashMap::new();
+        for (i, task) in tasks.iter().enumerate() {
+            by_project
+                .entry(project_ids[i / MAX_UPCOMING_TASKS])
+                .or_default()
+                .upcoming
+                .push((due, task));
+        }
+
+        let sections = project_sections(
+            &project_ids,
+            &mut by_project,
+            &ProjectTree::new(&[]),
+            MAX_TEXT_DISPLAY_LENGTH,
+        );
+        let length = sections
+            .iter()
+            .map(|section| section.chars().count())
+            .sum::<usize>();
+        assert!(length <= MAX_TEXT_DISPLAY_LENGTH - RESERVED_LENGTH);
+        assert!(sections.len() < MAX_PROJECTS);
+        // The last project shown says how many of its tasks did not fit
+        assert!(sections.last().unwrap().contains("-# …and"));
+    }
+
+    #[test]
+    fn project_sections_list_every_task_when_short() {
+        let due = DateTime::parse_from_rfc3339("2025-01-01T09:00:00Z").unwrap();
+        let task = Task::for_test("1", "Buy milk");
+        let mut by_project: HashMap<&str, ProjectReview> = HashMap::new();
+        by_project
+            .entry("1")
+            .or_default()
+            .upcoming
+            .push((due, &task));
+
+        let sections = project_sections(
+            &["1"],
+            &mut by_project,
+            &ProjectTree::new(&[]),
+            MAX_TEXT_DISPLAY_LENGTH,
+        );
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].contains("[Buy milk](https://app.todoist.com/app/task/1)"));
+        assert!(!sections[0].contains("more"));
+    }
+}
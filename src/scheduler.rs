@@ -0,0 +1,55 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+type Job<S> = Arc<dyn Fn(Arc<S>) -> JobFuture + Send + Sync>;
+
+/// Background jobs run periodically inside the bot process.
+///
+/// Jobs decide for themselves whether there is any work due each time they run, so schedules
+/// configured by users (such as digests) are checked by a job running every few seconds.
+pub struct Scheduler<S> {
+    jobs: Vec<(&'static str, Duration, Job<S>)>,
+}
+
+impl<S> Default for Scheduler<S> {
+    fn default() -> Self {
+        Self { jobs: Vec::new() }
+    }
+}
+
+impl<S> Scheduler<S>
+where
+    S: Send + Sync + 'static,
+{
+    /// Registers a job to run every `period`, starting immediately.
+    pub fn every<F, Fut>(&mut self, name: &'static str, period: Duration, job: F)
+    where
+        F: Fn(Arc<S>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let job: Job<S> = Arc::new(move |state| Box::pin(job(state)));
+        self.jobs.push((name, period, job));
+    }
+
+    /// Spawns a task for each job. Errors are logged and the job runs again at its next tick.
+    pub fn spawn(self, state: Arc<S>) {
+        for (name, period, job) in self.jobs {
+            let state = Arc::clone(&state);
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                loop {
+                    interval.tick().await;
+                    debug!("Running scheduled job {}", name);
+                    if let Err(e) = job(Arc::clone(&state)).await {
+                        warn!("Scheduled job {} failed: {}", name, e);
+                    }
+                }
+            });
+        }
+    }
+}
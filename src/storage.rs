@@ -0,0 +1,88 @@
+use std::env;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::fs;
+use tokio::sync::{RwLock, RwLockReadGuard};
+use tracing::{debug, info};
+
+/// The directory data is stored in if `DATA_DIR` is not set.
+const DEFAULT_DATA_DIR: &str = "data";
+
+/// A value persisted as a JSON file in the data directory.
+///
+/// The value is kept in memory and written back to disk after every update.
+pub struct JsonStore<T> {
+    path: PathBuf,
+    value: RwLock<T>,
+}
+
+impl<T> JsonStore<T>
+where
+    T: Serialize + DeserializeOwned + Default + Send + Sync,
+{
+    /// Loads the store from `name` in the data directory, starting from the default value if the
+    /// file does not exist yet.
+    pub async fn open(name: &str) -> Result<Self> {
+        let path = data_dir().join(name);
+        let value = match fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!(
+                    "{} does not exist, starting with an empty store",
+                    path.display()
+                );
+                T::default()
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            value: RwLock::new(value),
+        })
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.value.read().await
+    }
+
+    /// Applies the update to the value and writes it to disk.
+    pub async fn update<R>(&self, update: impl FnOnce(&mut T) -> R) -> Result<R> {
+        let mut value = self.value.write().await;
+        let result = update(&mut value);
+        self.persist(&value).await?;
+        Ok(result)
+    }
+
+    async fn persist(&self, value: &T) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // Write to a temporary file first so a crash never leaves a partially written store
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(value)?).await?;
+        fs::rename(&temp_path, &self.path).await?;
+        debug!("Saved {}", self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<T: Default> JsonStore<T> {
+    /// Creates a store starting from the default value, written to the temporary directory.
+    pub fn empty(name: &str) -> Self {
+        Self {
+            path: env::temp_dir().join(name),
+            value: RwLock::new(T::default()),
+        }
+    }
+}
+
+/// Gets the directory data is stored in, from `DATA_DIR`.
+pub fn data_dir() -> PathBuf {
+    env::var("DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_DATA_DIR))
+}
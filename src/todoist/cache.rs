@@ -0,0 +1,150 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use crate::todoist::{
+    self,
+    http::{
+        TodoistHttpClient,
+        models::{Label, Project, Section, Task},
+    },
+};
+
+/// How long cached projects, sections and labels are used before being fetched again.
+const STRUCTURE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long cached open tasks are used before being fetched again.
+const TASKS_TTL: Duration = Duration::from_secs(30);
+
+/// An in-memory index of the user's Todoist projects, sections, labels and open tasks.
+///
+/// Lookups return the cached value while it is fresh, so latency-sensitive callers such as
+/// autocomplete do not have to wait for the Todoist API.
+pub struct TodoistCache {
+    client: Arc<TodoistHttpClient>,
+    projects: Cached<Vec<Project>>,
+    sections: Cached<Vec<Section>>,
+    labels: Cached<Vec<Label>>,
+    tasks: Cached<Vec<Task>>,
+}
+
+struct Cached<T> {
+    ttl: Duration,
+    value: RwLock<Option<(Instant, Arc<T>)>>,
+}
+
+impl<T> Cached<T> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            value: RwLock::new(None),
+        }
+    }
+
+    async fn get_fresh(&self) -> Option<Arc<T>> {
+        self.value
+            .read()
+            .await
+            .as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, value)| Arc::clone(value))
+    }
+
+    async fn set(&self, value: T) -> Arc<T> {
+        let value = Arc::new(value);
+        *self.value.write().await = Some((Instant::now(), Arc::clone(&value)));
+        value
+    }
+
+    async fn invalidate(&self) {
+        *self.value.write().await = None;
+    }
+}
+
+impl TodoistCache {
+    pub fn new(client: Arc<TodoistHttpClient>) -> Self {
+        Self {
+            client,
+            projects: Cached::new(STRUCTURE_TTL),
+            sections: Cached::new(STRUCTURE_TTL),
+            labels: Cached::new(STRUCTURE_TTL),
+            tasks: Cached::new(TASKS_TTL),
+        }
+    }
+
+    pub async fn projects(&self) -> Result<Arc<Vec<Project>>> {
+        if let Some(projects) = self.projects.get_fresh().await {
+            return Ok(projects);
+        }
+        debug!("Refreshing cached projects");
+        let projects = todoist::get_projects(&self.client).await?;
+        Ok(self.projects.set(projects).await)
+    }
+
+    pub async fn sections(&self) -> Result<Arc<Vec<Section>>> {
+        if let Some(sections) = self.sections.get_fresh().await {
+            return Ok(sections);
+        }
+        debug!("Refreshing cached sections");
+        let sections = todoist::get_all_sections(&self.client).await?;
+        Ok(self.sections.set(sections).await)
+    }
+
+    pub async fn labels(&self) -> Result<Arc<Vec<Label>>> {
+        if let Some(labels) = self.labels.get_fresh().await {
+            return Ok(labels);
+        }
+        debug!("Refreshing cached labels");
+        let labels = todoist::get_labels(&self.client).await?;
+        Ok(self.labels.set(labels).await)
+    }
+
+    pub async fn open_tasks(&self) -> Result<Arc<Vec<Task>>> {
+        if let Some(tasks) = self.tasks.get_fresh().await {
+            return Ok(tasks);
+        }
+        debug!("Refreshing cached open tasks");
+        let tasks = todoist::get_open_tasks(&self.client).await?;
+        Ok(self.tasks.set(tasks).await)
+    }
+
+    /// Discards the cached open tasks so the next lookup fetches them again.
+    pub async fn invalidate_tasks(&self) {
+        self.tasks.invalidate().await;
+    }
+
+    /// Discards the cached projects, sections and labels so the next lookup fetches them again.
+    pub async fn invalidate_structure(&self) {
+        self.projects.invalidate().await;
+        self.sections.invalidate().await;
+        self.labels.invalidate().await;
+    }
+
+    /// Periodically refreshes the cache in the background so lookups rarely have to wait for the
+    /// Todoist API.
+    pub fn spawn_refresh(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TASKS_TTL / 2);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.refresh().await {
+                    warn!("Failed to refresh the Todoist cache: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let tasks = todoist::get_open_tasks(&self.client).await?;
+        self.tasks.set(tasks).await;
+        self.projects().await?;
+        self.sections().await?;
+        self.labels().await?;
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code, reason = "Models for Todoist HTTP API responses")]
 use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset};
 use chrono_tz::Tz;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::get_timezone_override;
@@ -13,6 +13,12 @@ pub struct CursorResponse<T> {
 }
 
 #[derive(Debug, Deserialize)]
+pub struct CompletedTasksResponse {
+    pub items: Vec<Task>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct Project {
     pub id: String,
     pub can_assign_tasks: bool,
@@ -37,6 +43,12 @@ pub struct Project {
     pub is_shared: bool,
 }
 
+impl Project {
+    pub fn get_url(&self) -> String {
+        format!("https://app.todoist.com/app/project/{}", self.id)
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Due {
     pub date: String,
@@ -116,19 +128,19 @@ impl TryFrom<Due> for DateTime<FixedOffset> {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Deadline {
     pub date: String,
     pub lang: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Duration {
     pub amount: i64,
     pub unit: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Task {
     pub user_id: String,
     pub id: String,
@@ -161,7 +173,39 @@ impl Task {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[cfg(test)]
+impl Task {
+    /// Creates an open task in the inbox with no due date, for tests.
+    pub fn for_test(id: &str, content: &str) -> Self {
+        Self {
+            user_id: "1".to_string(),
+            id: id.to_string(),
+            project_id: "inbox".to_string(),
+            section_id: None,
+            parent_id: None,
+            added_by_uid: None,
+            assigned_by_uid: None,
+            responsible_uid: None,
+            labels: Vec::new(),
+            deadline: None,
+            duration: None,
+            checked: false,
+            is_deleted: false,
+            added_at: "2025-01-01T00:00:00Z".to_string(),
+            completed_at: None,
+            updated_at: None,
+            due: None,
+            priority: 1,
+            child_order: 0,
+            content: content.to_string(),
+            description: String::new(),
+            day_order: 0,
+            is_collapsed: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct Section {
     pub id: String,
     pub user_id: String,
@@ -175,3 +219,38 @@ pub struct Section {
     pub is_deleted: bool,
     pub is_collapsed: bool,
 }
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FileAttachment {
+    pub file_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<String>,
+    pub file_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_state: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Comment {
+    pub id: String,
+    pub posted_uid: Option<String>,
+    pub content: String,
+    pub file_attachment: Option<FileAttachment>,
+    pub posted_at: String,
+    pub is_deleted: bool,
+    pub item_id: Option<String>,
+    pub project_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Label {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub order: Option<i64>,
+    pub is_favorite: bool,
+}
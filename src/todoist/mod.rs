@@ -1,6 +1,7 @@
 use anyhow::{Result, anyhow};
-use chrono::{DateTime, FixedOffset, Local, Utc};
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, Utc};
 use chrono_tz::Tz;
+use reqwest::multipart::{Form, Part};
 use serde::Serialize;
 use std::result::Result as StdResult;
 use time::OffsetDateTime;
@@ -8,10 +9,13 @@ use tracing::debug;
 
 use crate::todoist::http::{
     TodoistHttpClient,
-    models::{Project, Section, Task},
+    models::{Comment, CompletedTasksResponse, FileAttachment, Label, Project, Section, Task},
 };
 
+pub mod cache;
 pub mod http;
+pub mod tree;
+pub mod webhook;
 
 pub async fn get_tasks_due_today(
     client: &TodoistHttpClient,
@@ -65,6 +69,68 @@ pub async fn get_tasks_due_today(
     Ok(today_tasks)
 }
 
+/// Gets the open tasks that were due before today, oldest first.
+pub async fn get_overdue_tasks(
+    client: &TodoistHttpClient,
+    timezone: Option<Tz>,
+) -> Result<Vec<Task>> {
+    let all_tasks = client.get_all::<Task>("/tasks").await?;
+
+    let today_date = match timezone {
+        Some(tz) => Utc::now().with_timezone(&tz).date_naive(),
+        None => Utc::now().with_timezone(&Local).date_naive(),
+    };
+
+    let mut overdue_tasks = all_tasks
+        .into_iter()
+        .filter_map(|task| {
+            let due_date: DateTime<FixedOffset> = task.due.clone()?.try_into().ok()?;
+            let due_date_in_tz = match timezone {
+                Some(tz) => due_date.with_timezone(&tz).date_naive(),
+                None => due_date.with_timezone(&Local).date_naive(),
+            };
+            (due_date_in_tz < today_date).then_some((due_date, task))
+        })
+        .collect::<Vec<_>>();
+    overdue_tasks.sort_by_key(|(due_date, _)| *due_date);
+
+    Ok(overdue_tasks.into_iter().map(|(_, task)| task).collect())
+}
+
+/// Gets the tasks completed between `since` and `until`.
+pub async fn get_completed_tasks(
+    client: &TodoistHttpClient,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<Task>> {
+    let mut tasks = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut query = vec![
+            ("since", since.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ("until", until.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ];
+        if let Some(cursor) = cursor.take() {
+            query.push(("cursor", cursor));
+        }
+        let response: CompletedTasksResponse = client
+            .get("/tasks/completed/by_completion_date")
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        tasks.extend(response.items);
+        cursor = response.next_cursor;
+        debug!("Next completed tasks cursor: {:?}", cursor);
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(tasks)
+}
+
 pub async fn get_projects(client: &TodoistHttpClient) -> Result<Vec<Project>> {
     client
         .get_all::<Project>("/projects")
@@ -79,6 +145,42 @@ pub async fn get_sections(client: &TodoistHttpClient, project_id: &str) -> Resul
         .map_err(|e| anyhow!(e))
 }
 
+/// Gets the sections of all projects.
+pub async fn get_all_sections(client: &TodoistHttpClient) -> Result<Vec<Section>> {
+    client
+        .get_all::<Section>("/sections")
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+pub async fn get_labels(client: &TodoistHttpClient) -> Result<Vec<Label>> {
+    client
+        .get_all::<Label>("/labels")
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct NewLabel {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_favorite: Option<bool>,
+}
+
+pub async fn create_label(client: &TodoistHttpClient, new_label: NewLabel) -> Result<Label> {
+    client
+        .post("/labels")
+        .json(&new_label)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
 #[derive(Serialize, Debug, Default)]
 pub struct NewTask {
     pub content: String,
@@ -111,6 +213,51 @@ pub async fn create_task(client: &TodoistHttpClient, new_task: NewTask) -> Resul
         .map_err(|e| anyhow!(e))
 }
 
+#[derive(Serialize, Debug, Default)]
+pub struct QuickAddTask {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_reminder: Option<bool>,
+}
+
+/// Creates a task using Todoist's Quick Add syntax, parsing dates, projects, labels and priorities
+/// from the text the same way the Todoist app does.
+pub async fn quick_add_task(client: &TodoistHttpClient, quick_add: QuickAddTask) -> Result<Task> {
+    client
+        .post("/tasks/quick")
+        .json(&quick_add)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+/// Returns true if the text appears to use Quick Add syntax, i.e. it contains a `#project`,
+/// an `@label` or a `p1`-`p4` priority.
+///
+/// `@name` is only taken as a label if it is one of `labels`, ignoring case, as it is more likely
+/// to mention someone, e.g. "ping @Sam about the docs".
+pub fn is_quick_add_syntax(text: &str, labels: &[&str]) -> bool {
+    text.split_whitespace().any(|token| {
+        let mut chars = token.chars();
+        match chars.next() {
+            // Skip things like "#12" which are more likely to be issue or PR numbers
+            Some('#') => chars.next().is_some_and(|c| c.is_alphabetic()),
+            Some('@') => labels
+                .iter()
+                .any(|label| label.eq_ignore_ascii_case(chars.as_str())),
+            Some('p') | Some('P') => matches!(chars.as_str(), "1" | "2" | "3" | "4"),
+            _ => false,
+        }
+    })
+}
+
 #[derive(Serialize, Debug, Default)]
 pub struct MoveTask {
     #[serde(skip_serializing)]
@@ -134,6 +281,43 @@ pub async fn move_task(client: &TodoistHttpClient, move_task: MoveTask) -> Resul
         .map_err(|e| anyhow!(e))
 }
 
+pub async fn close_task(client: &TodoistHttpClient, task_id: &str) -> Result<()> {
+    client
+        .post(&format!("/tasks/{}/close", task_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+pub async fn reopen_task(client: &TodoistHttpClient, task_id: &str) -> Result<()> {
+    client
+        .post(&format!("/tasks/{}/reopen", task_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Gets all open tasks.
+pub async fn get_open_tasks(client: &TodoistHttpClient) -> Result<Vec<Task>> {
+    client
+        .get_all::<Task>("/tasks")
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+pub async fn get_task(client: &TodoistHttpClient, task_id: &str) -> Result<Task> {
+    client
+        .get(&format!("/tasks/{}", task_id))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
 #[derive(Serialize, Debug, Default)]
 pub struct UpdateTaskBody {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -146,8 +330,12 @@ pub struct UpdateTaskBody {
     pub priority: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub due_string: Option<String>,
-    #[serde(with = "time::serde::rfc3339::option")]
+    #[serde(
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub due_date: Option<OffsetDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub due_datetime: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub assignee_uid: Option<Option<String>>,
@@ -158,3 +346,114 @@ pub struct UpdateTaskBody {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deadline_date: Option<Option<String>>,
 }
+
+/// Updates the task, changing only the fields that are set.
+pub async fn update_task(
+    client: &TodoistHttpClient,
+    task_id: &str,
+    update: UpdateTaskBody,
+) -> Result<Task> {
+    client
+        .post(&format!("/tasks/{}", task_id))
+        .json(&update)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+/// Uploads a file to Todoist so it can be attached to a comment.
+pub async fn upload_file(
+    client: &TodoistHttpClient,
+    file_name: &str,
+    content_type: Option<&str>,
+    data: Vec<u8>,
+) -> Result<FileAttachment> {
+    let mut part = Part::bytes(data).file_name(file_name.to_string());
+    if let Some(content_type) = content_type {
+        part = part.mime_str(content_type)?;
+    }
+    let form = Form::new()
+        .text("file_name", file_name.to_string())
+        .part("file", part);
+
+    client
+        .post("/uploads")
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct NewComment {
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<FileAttachment>,
+}
+
+pub async fn create_comment(
+    client: &TodoistHttpClient,
+    new_comment: NewComment,
+) -> Result<Comment> {
+    client
+        .post("/comments")
+        .json(&new_comment)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+/// Gets the comments on the task, oldest first.
+pub async fn get_task_comments(client: &TodoistHttpClient, task_id: &str) -> Result<Vec<Comment>> {
+    client
+        .get_all::<Comment>(&format!("/comments?task_id={}", task_id))
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: &[&str] = &["phone", "Errands"];
+
+    #[test]
+    fn quick_add_syntax() {
+        assert!(is_quick_add_syntax("Buy milk #Groceries", LABELS));
+        assert!(is_quick_add_syntax("Call mom @phone", LABELS));
+        assert!(is_quick_add_syntax("Pick up parcel @errands", LABELS));
+        assert!(is_quick_add_syntax("Pay rent tomorrow p1", LABELS));
+        assert!(is_quick_add_syntax("Pay rent P4", LABELS));
+    }
+
+    #[test]
+    fn not_quick_add_syntax() {
+        assert!(!is_quick_add_syntax("Buy milk", LABELS));
+        assert!(!is_quick_add_syntax("Review PR #123", LABELS));
+        assert!(!is_quick_add_syntax("Email me @ 5", LABELS));
+        assert!(!is_quick_add_syntax("Fix p5 and p12", LABELS));
+        assert!(!is_quick_add_syntax("Read chapter p", LABELS));
+    }
+
+    #[test]
+    fn mentions_are_not_quick_add_syntax() {
+        assert!(!is_quick_add_syntax("ping @Sam about the docs", LABELS));
+        assert!(!is_quick_add_syntax("Ask @phones about it", LABELS));
+        assert!(!is_quick_add_syntax("Call mom @phone", &[]));
+        // A mention doesn't stop other Quick Add syntax from being recognised
+        assert!(is_quick_add_syntax("ping @Sam about the docs p2", LABELS));
+    }
+}
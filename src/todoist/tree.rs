@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::todoist::http::models::Project;
+
+/// The separator between project names in a project's path.
+pub const PATH_SEPARATOR: &str = " / ";
+
+/// The active projects arranged by their parent, in the order they appear in Todoist.
+pub struct ProjectTree<'a> {
+    entries: Vec<ProjectEntry<'a>>,
+}
+
+/// A project in a [`ProjectTree`], along with its position in the hierarchy.
+pub struct ProjectEntry<'a> {
+    pub project: &'a Project,
+    /// The number of ancestors of the project, with top level projects at depth 0.
+    pub depth: usize,
+    /// The names of the project and its ancestors, e.g. "Work / Client A / Backlog".
+    pub path: String,
+}
+
+impl<'a> ProjectTree<'a> {
+    /// Builds the tree from a flat list of projects, skipping archived and deleted projects.
+    ///
+    /// Projects whose parent is missing or inactive are shown at the top level.
+    pub fn new(projects: &'a [Project]) -> Self {
+        let active = projects
+            .iter()
+            .filter(|project| !project.is_archived && !project.is_deleted)
+            .collect::<Vec<_>>();
+        let ids = active
+            .iter()
+            .map(|project| project.id.as_str())
+            .collect::<HashSet<_>>();
+
+        let mut children: HashMap<Option<&str>, Vec<&Project>> = HashMap::new();
+        for project in &active {
+            let parent = project
+                .parent_id
+                .as_deref()
+                .filter(|parent| ids.contains(parent));
+            children.entry(parent).or_default().push(project);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by_key(|project| project.child_order);
+        }
+
+        let mut entries = Vec::with_capacity(active.len());
+        let mut visited = HashSet::new();
+        let mut stack = children
+            .get(&None)
+            .map(|roots| {
+                roots
+                    .iter()
+                    .rev()
+                    .map(|project| (*project, 0, project.name.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        while let Some((project, depth, path)) = stack.pop() {
+            // Guard against a malformed hierarchy containing a cycle
+            if !visited.insert(project.id.as_str()) {
+                continue;
+            }
+            if let Some(descendants) = children.get(&Some(project.id.as_str())) {
+                for child in descendants.iter().rev() {
+                    let child_path = format!("{}{}{}", path, PATH_SEPARATOR, child.name);
+                    stack.push((*child, depth + 1, child_path));
+                }
+            }
+            entries.push(ProjectEntry {
+                project,
+                depth,
+                path,
+            });
+        }
+
+        Self { entries }
+    }
+
+    /// Iterates over the projects depth-first, with children following their parent.
+    pub fn iter(&self) -> impl Iterator<Item = &ProjectEntry<'a>> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, project_id: &str) -> Option<&ProjectEntry<'a>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.project.id == project_id)
+    }
+
+    /// Gets the path of the project, e.g. "Work / Client A / Backlog".
+    pub fn path(&self, project_id: &str) -> Option<&str> {
+        self.get(project_id).map(|entry| entry.path.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(id: &str, name: &str, parent_id: Option<&str>, child_order: i64) -> Project {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "can_assign_tasks": false,
+            "child_order": child_order,
+            "color": "grey",
+            "creator_uid": null,
+            "created_at": "2025-01-01T00:00:00Z",
+            "is_archived": false,
+            "is_deleted": false,
+            "is_favorite": false,
+            "is_frozen": false,
+            "name": name,
+            "updated_at": null,
+            "view_style": "list",
+            "default_order": 0,
+            "description": "",
+            "public_key": "",
+            "role": null,
+            "parent_id": parent_id,
+            "inbox_project": false,
+            "is_collapsed": false,
+            "is_shared": false,
+        }))
+        .unwrap()
+    }
+
+    fn paths(tree: &ProjectTree) -> Vec<(String, usize)> {
+        tree.iter()
+            .map(|entry| (entry.path.clone(), entry.depth))
+            .collect()
+    }
+
+    #[test]
+    fn orders_children_after_their_parent() {
+        let projects = vec![
+            project("3", "Backlog", Some("2"), 0),
+            project("4", "Home", None, 1),
+            project("2", "Client A", Some("1"), 0),
+            project("1", "Work", None, 0),
+            project("5", "Client B", Some("1"), 1),
+        ];
+        let tree = ProjectTree::new(&projects);
+        assert_eq!(
+            paths(&tree),
+            vec![
+                ("Work".to_string(), 0),
+                ("Work / Client A".to_string(), 1),
+                ("Work / Client A / Backlog".to_string(), 2),
+                ("Work / Client B".to_string(), 1),
+                ("Home".to_string(), 0),
+            ]
+        );
+        assert_eq!(tree.path("3"), Some("Work / Client A / Backlog"));
+    }
+
+    #[test]
+    fn skips_inactive_projects() {
+        let mut archived = project("1", "Old", None, 0);
+        archived.is_archived = true;
+        let mut deleted = project("3", "Gone", None, 2);
+        deleted.is_deleted = true;
+        let projects = vec![
+            archived,
+            project("2", "Child", Some("1"), 0),
+            deleted,
+            project("4", "Home", None, 1),
+        ];
+        let tree = ProjectTree::new(&projects);
+        // Children of an inactive project are shown at the top level
+        assert_eq!(
+            paths(&tree),
+            vec![("Child".to_string(), 0), ("Home".to_string(), 0)]
+        );
+        assert!(tree.get("1").is_none());
+    }
+
+    #[test]
+    fn projects_in_a_cycle_are_not_shown() {
+        let projects = vec![
+            project("1", "A", Some("2"), 0),
+            project("2", "B", Some("1"), 0),
+            project("3", "Home", None, 0),
+        ];
+        let tree = ProjectTree::new(&projects);
+        assert_eq!(paths(&tree), vec![("Home".to_string(), 0)]);
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::hmac;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid signature")]
+    Signature,
+}
+
+/// Verifies the `X-Todoist-Hmac-SHA256` signature of Todoist webhook requests, which is the
+/// base64 encoded HMAC-SHA256 of the body using the app's client secret.
+pub struct WebhookVerifier {
+    key: hmac::Key,
+}
+
+impl WebhookVerifier {
+    pub fn new(client_secret: &str) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, client_secret.as_bytes()),
+        }
+    }
+
+    pub fn verify(&self, signature: &str, body: &[u8]) -> Result<()> {
+        let signature = STANDARD
+            .decode(signature.trim())
+            .map_err(|_| anyhow!(Error::Signature))?;
+        hmac::verify(&self.key, body, &signature).map_err(|_| anyhow!(Error::Signature))
+    }
+}
+
+/// An event delivered to the webhook, e.g. `item:completed`.
+#[derive(Deserialize, Debug)]
+pub struct WebhookEvent {
+    pub event_name: String,
+    pub event_data: serde_json::Value,
+}
+
+/// The fields of a task in `item:*` events that the bot uses.
+#[derive(Deserialize, Debug)]
+pub struct WebhookItem {
+    pub id: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub project_id: String,
+}
+
+impl WebhookEvent {
+    /// Gets the task the event is about, if it is an `item:*` event.
+    pub fn item(&self) -> Option<WebhookItem> {
+        if !self.event_name.starts_with("item:") {
+            return None;
+        }
+        serde_json::from_value(self.event_data.clone()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_SECRET: &str = "0123456789abcdef";
+    const BODY: &[u8] = br#"{"event_name":"item:completed","user_id":"2671355","event_data":{"id":"2995104339","content":"Buy milk","project_id":"2203306141"}}"#;
+    const SIGNATURE: &str = "VR61UmJ28SXk7PP8w/MmwF7ywCqooL/lmlFXepKVTyE=";
+
+    #[test]
+    fn verifies_valid_signature() {
+        let verifier = WebhookVerifier::new(CLIENT_SECRET);
+        assert!(verifier.verify(SIGNATURE, BODY).is_ok());
+        assert!(verifier.verify(&format!(" {}\n", SIGNATURE), BODY).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_signature() {
+        let verifier = WebhookVerifier::new(CLIENT_SECRET);
+        assert!(verifier.verify(SIGNATURE, b"{}").is_err());
+        assert!(verifier.verify("not base64!", BODY).is_err());
+        assert!(
+            WebhookVerifier::new("another secret")
+                .verify(SIGNATURE, BODY)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parses_item_events() {
+        let event: WebhookEvent = serde_json::from_slice(BODY).unwrap();
+        let item = event.item().unwrap();
+        assert_eq!(item.id, "2995104339");
+        assert_eq!(item.content, "Buy milk");
+        assert_eq!(item.project_id, "2203306141");
+
+        let event = WebhookEvent {
+            event_name: "project:updated".to_string(),
+            event_data: serde_json::json!({ "id": "1" }),
+        };
+        assert!(event.item().is_none());
+    }
+}